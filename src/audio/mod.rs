@@ -0,0 +1,236 @@
+//! Sound effects and music
+//!
+//! The game doesn't play sounds by itself: it records the sounds caused by
+//! each update (see `Game::sounds`), and the `Audio` subsystem plays them.
+//! This keeps the game logic independent from the audio device, so it can
+//! run headlessly.
+//!
+//! The sound effects and the music are generated when the game starts (see `synth`)
+
+#[cfg(feature = "audio")]
+mod openal;
+pub mod synth;
+mod wav;
+
+use std::collections::HashMap;
+use std::env;
+use std::f64;
+use std::path::PathBuf;
+
+/// The sounds fired by the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    /// The player fired a bullet
+    Shoot,
+    /// An enemy was hit by a bullet and exploded
+    EnemyExplosion,
+    /// The player collided with an enemy
+    PlayerDeath,
+    /// The hum of the engine, played in a loop while boosting
+    Thrust,
+    /// A new wave of enemies was announced
+    WaveStart,
+    /// A player collected a pickup
+    Pickup
+}
+
+impl Sound {
+    /// Returns every sound
+    pub fn all() -> Vec<Sound> {
+        vec![Sound::Shoot, Sound::EnemyExplosion, Sound::PlayerDeath, Sound::Thrust, Sound::WaveStart,
+             Sound::Pickup]
+    }
+
+    /// Returns the name of the file containing the generated sound
+    pub fn file_name(&self) -> &'static str {
+        match *self {
+            Sound::Shoot => "shoot.wav",
+            Sound::EnemyExplosion => "enemy_explosion.wav",
+            Sound::PlayerDeath => "player_death.wav",
+            Sound::Thrust => "thrust.wav",
+            Sound::WaveStart => "wave_start.wav",
+            Sound::Pickup => "pickup.wav"
+        }
+    }
+
+    /// Returns the minimum amount of seconds between two plays of the sound
+    ///
+    /// Bullets are fired a hundred times per second, so playing every shot
+    /// would only produce noise
+    fn min_interval(&self) -> f64 {
+        match *self {
+            Sound::Shoot => 0.08,
+            Sound::EnemyExplosion => 0.03,
+            Sound::PlayerDeath | Sound::Thrust | Sound::WaveStart | Sound::Pickup => 0.0
+        }
+    }
+}
+
+/// The name of the file containing the generated background music
+pub const MUSIC: &'static str = "music.wav";
+
+/// The amount by which the volume changes with each key press
+const VOLUME_STEP: f32 = 0.1;
+
+/// A backend plays sounds on an audio device
+pub trait Backend {
+    /// Plays the sound once at the given volume (between 0 and 1)
+    fn play(&mut self, sound: Sound, volume: f32);
+
+    /// Starts playing the music in the given file in a loop
+    fn play_music(&mut self, path: &str, volume: f32);
+
+    /// Changes the volume of the music (between 0 and 1)
+    fn set_music_volume(&mut self, volume: f32);
+
+    /// Starts playing the sound in a loop, or changes its volume if it is playing already
+    fn start_loop(&mut self, sound: Sound, volume: f32);
+
+    /// Stops playing the looping sound
+    fn stop_loop(&mut self, sound: Sound);
+}
+
+/// A backend that doesn't play anything, used when there is no audio device
+pub struct NullBackend;
+
+impl Backend for NullBackend {
+    fn play(&mut self, _: Sound, _: f32) { }
+    fn play_music(&mut self, _: &str, _: f32) { }
+    fn set_music_volume(&mut self, _: f32) { }
+    fn start_loop(&mut self, _: Sound, _: f32) { }
+    fn stop_loop(&mut self, _: Sound) { }
+}
+
+/// The audio subsystem, with its volume controls
+pub struct Audio {
+    backend: Box<Backend>,
+    /// The volume of everything, between 0 and 1
+    pub volume: f32,
+    /// The volume of the sound effects, relative to `volume`
+    pub effects_volume: f32,
+    /// The volume of the music, relative to `volume`
+    pub music_volume: f32,
+    muted: bool,
+    /// Whether the engine hum is playing
+    thrusting: bool,
+    /// Seconds since each sound was last played
+    since_played: HashMap<Sound, f64>
+}
+
+impl Audio {
+    /// Returns the audio subsystem, using the audio device if there is one
+    /// and a `NullBackend` otherwise. The music starts playing right away
+    pub fn new() -> Audio {
+        // Generate the sounds in a temporary directory, where the backend will load them
+        // from. The files are only written the first time, or after the sounds change
+        let directory = env::temp_dir().join("rocket-sounds");
+        if let Err(e) = synth::export(&directory) {
+            println!("{}", e);
+        }
+
+        let music = directory.join(MUSIC);
+        let mut audio = Audio::with_backend(default_backend(directory));
+        let volume = audio.music_volume();
+        audio.backend.play_music(&music.to_string_lossy(), volume);
+        audio
+    }
+
+    /// Returns the audio subsystem using the given backend
+    pub fn with_backend(backend: Box<Backend>) -> Audio {
+        Audio {
+            backend: backend,
+            volume: 0.8,
+            effects_volume: 1.0,
+            music_volume: 0.5,
+            muted: false,
+            thrusting: false,
+            since_played: HashMap::new()
+        }
+    }
+
+    /// Plays the sounds fired by the game during the last update
+    ///
+    /// `dt` is the amount of seconds that have passed since the last update
+    pub fn update(&mut self, dt: f64, sounds: &[Sound]) {
+        for (_, elapsed) in self.since_played.iter_mut() {
+            *elapsed += dt;
+        }
+
+        let volume = if self.muted { 0.0 } else { self.volume * self.effects_volume };
+        for &sound in sounds {
+            // Skip sounds that were played too recently
+            let elapsed = self.since_played.entry(sound).or_insert(f64::MAX);
+            if *elapsed < sound.min_interval() {
+                continue;
+            }
+            *elapsed = 0.0;
+
+            if volume > 0.0 {
+                self.backend.play(sound, volume);
+            }
+        }
+    }
+
+    /// Starts or stops the hum of the engine
+    pub fn set_thrust(&mut self, thrusting: bool) {
+        if thrusting != self.thrusting {
+            self.thrusting = thrusting;
+            self.update_loops();
+        }
+    }
+
+    /// Mutes or unmutes everything
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.update_music_volume();
+        self.update_loops();
+    }
+
+    /// Increases (or decreases, if `steps` is negative) the volume of everything
+    pub fn change_volume(&mut self, steps: i32) {
+        self.volume = (self.volume + steps as f32 * VOLUME_STEP).max(0.0).min(1.0);
+        self.update_music_volume();
+        self.update_loops();
+    }
+
+    /// Returns the effective volume of the music
+    fn music_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume * self.music_volume }
+    }
+
+    /// Applies the current volume to the music
+    fn update_music_volume(&mut self) {
+        let volume = self.music_volume();
+        self.backend.set_music_volume(volume);
+    }
+
+    /// Starts, stops or changes the volume of the looping sounds
+    fn update_loops(&mut self) {
+        if self.thrusting && !self.muted {
+            let volume = self.volume * self.effects_volume;
+            self.backend.start_loop(Sound::Thrust, volume);
+        } else {
+            self.backend.stop_loop(Sound::Thrust);
+        }
+    }
+}
+
+/// Returns the OpenAL backend if there is an audio device, or a `NullBackend` otherwise
+///
+/// The sound effects are loaded from the given directory
+#[cfg(feature = "audio")]
+fn default_backend(directory: PathBuf) -> Box<Backend> {
+    match openal::OpenAlBackend::new(directory) {
+        Some(backend) => Box::new(backend),
+        None => {
+            println!("No audio device found, the game will be silent");
+            Box::new(NullBackend)
+        }
+    }
+}
+
+/// Returns a `NullBackend`, since the game was built without audio support
+#[cfg(not(feature = "audio"))]
+fn default_backend(_: PathBuf) -> Box<Backend> {
+    Box::new(NullBackend)
+}
@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ears::{self, AudioController, Music, SoundData};
+
+use super::{Backend, Sound};
+
+/// The amount of copies of each sound that can be playing at the same time
+const VOICES: usize = 4;
+
+/// A backend that plays sounds through OpenAL, using the `ears` library
+pub struct OpenAlBackend {
+    /// The directory containing the sound effects
+    directory: PathBuf,
+    /// The loaded sounds. Each sound has several voices, so it can overlap with itself
+    sounds: HashMap<Sound, Vec<ears::Sound>>,
+    /// The voice of each sound that will be used next
+    next_voice: HashMap<Sound, usize>,
+    /// Sounds whose file couldn't be loaded
+    missing: Vec<Sound>,
+    /// The sounds playing in a loop
+    loops: HashMap<Sound, ears::Sound>,
+    music: Option<Music>
+}
+
+impl OpenAlBackend {
+    /// Returns a backend for the default audio device, if there is one
+    ///
+    /// The sound effects are loaded from the given directory
+    pub fn new(directory: PathBuf) -> Option<OpenAlBackend> {
+        if !ears::init() {
+            return None;
+        }
+
+        Some(OpenAlBackend {
+            directory: directory,
+            sounds: HashMap::new(),
+            next_voice: HashMap::new(),
+            missing: vec![],
+            loops: HashMap::new(),
+            music: None
+        })
+    }
+
+    /// Returns the path of the file containing the sound
+    fn path(&self, sound: Sound) -> String {
+        self.directory.join(sound.file_name()).to_string_lossy().into_owned()
+    }
+
+    /// Loads the voices of the sound, unless they are loaded already
+    ///
+    /// Returns false if the file of the sound can't be loaded
+    fn load(&mut self, sound: Sound) -> bool {
+        if self.sounds.contains_key(&sound) {
+            return true;
+        }
+        if self.missing.contains(&sound) {
+            return false;
+        }
+
+        let path = self.path(sound);
+        let voices = SoundData::new(&path).and_then(|data| {
+            let data = Rc::new(RefCell::new(data));
+            (0..VOICES).map(|_| ears::Sound::new_with_data(data.clone())).collect()
+        });
+
+        match voices {
+            Some(voices) => {
+                self.sounds.insert(sound, voices);
+                true
+            }
+            None => {
+                println!("Unable to load {}", path);
+                self.missing.push(sound);
+                false
+            }
+        }
+    }
+}
+
+impl Backend for OpenAlBackend {
+    fn play(&mut self, sound: Sound, volume: f32) {
+        if !self.load(sound) {
+            return;
+        }
+
+        let next = self.next_voice.entry(sound).or_insert(0);
+        let voice = &mut self.sounds.get_mut(&sound).unwrap()[*next];
+        *next = (*next + 1) % VOICES;
+
+        voice.set_volume(volume);
+        voice.play();
+    }
+
+    fn play_music(&mut self, path: &str, volume: f32) {
+        self.music = Music::new(path);
+        match self.music {
+            Some(ref mut music) => {
+                music.set_looping(true);
+                music.set_volume(volume);
+                music.play();
+            }
+            None => println!("Unable to load {}", path)
+        }
+    }
+
+    fn set_music_volume(&mut self, volume: f32) {
+        if let Some(ref mut music) = self.music {
+            music.set_volume(volume);
+        }
+    }
+
+    fn start_loop(&mut self, sound: Sound, volume: f32) {
+        if !self.loops.contains_key(&sound) {
+            let path = self.path(sound);
+            match ears::Sound::new(&path) {
+                Some(mut looping) => {
+                    looping.set_looping(true);
+                    looping.play();
+                    self.loops.insert(sound, looping);
+                }
+                None => {
+                    println!("Unable to load {}", path);
+                    return;
+                }
+            }
+        }
+
+        if let Some(looping) = self.loops.get_mut(&sound) {
+            looping.set_volume(volume);
+        }
+    }
+
+    fn stop_loop(&mut self, sound: Sound) {
+        if let Some(mut looping) = self.loops.remove(&sound) {
+            looping.stop();
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Procedural generation of retro sound effects
+//!
+//! Instead of shipping audio files, every sound effect is described by a few
+//! parameters (waveform, pitch sweep and envelope) and rendered to PCM when
+//! the game starts. The music is a loop of notes rendered the same way
+
+use std::f64;
+use std::fs;
+use std::i16;
+use std::path::Path;
+
+use rand::Rng;
+
+use rng::GameRng;
+use super::Sound;
+use super::wav;
+
+/// The amount of samples per second of the generated sounds
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// The notes of the background music, in Hz: a bass arpeggio going through
+/// A minor, G major, F major and E minor
+const MELODY: [f64; 16] = [110.0, 164.81, 220.0, 164.81, 98.0, 146.83, 196.0, 146.83,
+                           87.31, 130.81, 174.61, 130.81, 82.41, 123.47, 164.81, 123.47];
+
+/// The duration of each note of the music, in seconds
+const NOTE: f64 = 0.3;
+
+/// The shape of the wave of a sound
+#[derive(Clone, Copy)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Sawtooth,
+    /// Random values, changing once per period. Lower frequencies give a rougher noise
+    Noise
+}
+
+/// The parameters of a generated sound
+pub struct Params {
+    pub waveform: Waveform,
+    /// The frequency at the start and at the end of the sound, in Hz. The
+    /// frequency slides exponentially from one to the other, which sounds linear
+    pub frequency: [f64; 2],
+    /// The duration of the sound, in seconds
+    pub duration: f64,
+    /// The time it takes to fade in, in seconds
+    pub attack: f64,
+    /// The time it takes to fade out at the end, in seconds
+    pub release: f64,
+    /// The volume, between 0 and 1
+    pub volume: f64
+}
+
+impl Sound {
+    /// Returns the parameters used to generate the sound
+    pub fn params(&self) -> Params {
+        match *self {
+            // A quick downwards sweep sounds like a laser
+            Sound::Shoot => Params {
+                waveform: Waveform::Square,
+                frequency: [1400.0, 250.0],
+                duration: 0.12,
+                attack: 0.0,
+                release: 0.08,
+                volume: 0.25
+            },
+            // Explosions are bursts of noise that get deeper as they fade
+            Sound::EnemyExplosion => Params {
+                waveform: Waveform::Noise,
+                frequency: [3000.0, 400.0],
+                duration: 0.5,
+                attack: 0.0,
+                release: 0.45,
+                volume: 0.6
+            },
+            Sound::PlayerDeath => Params {
+                waveform: Waveform::Noise,
+                frequency: [1500.0, 60.0],
+                duration: 1.4,
+                attack: 0.01,
+                release: 1.2,
+                volume: 0.8
+            },
+            // The hum is played in a loop, so it lasts a whole number of
+            // periods and has no envelope, to avoid clicks
+            Sound::Thrust => Params {
+                waveform: Waveform::Sawtooth,
+                frequency: [55.0, 55.0],
+                duration: 1.0,
+                attack: 0.0,
+                release: 0.0,
+                volume: 0.2
+            },
+            // A short upwards sweep, like collecting a coin
+            Sound::Pickup => Params {
+                waveform: Waveform::Square,
+                frequency: [990.0, 1980.0],
+                duration: 0.15,
+                attack: 0.0,
+                release: 0.05,
+                volume: 0.2
+            },
+            // A rising tone warns that enemies are about to appear
+            Sound::WaveStart => Params {
+                waveform: Waveform::Sine,
+                frequency: [330.0, 880.0],
+                duration: 0.6,
+                attack: 0.05,
+                release: 0.3,
+                volume: 0.4
+            }
+        }
+    }
+}
+
+/// Renders the sound to 16 bits PCM samples (mono)
+pub fn render(params: &Params) -> Vec<i16> {
+    // The noise is seeded, so the same parameters always give the same sound
+    let mut rng = GameRng::new(0);
+    let mut noise = 0.0;
+
+    let samples = (params.duration * SAMPLE_RATE as f64) as usize;
+    let sweep = params.frequency[1] / params.frequency[0];
+    let mut phase = 0.0f64;
+    let mut output = Vec::with_capacity(samples);
+
+    for i in 0..samples {
+        let t = i as f64 / SAMPLE_RATE as f64;
+
+        // Advance the phase according to the current frequency
+        let frequency = params.frequency[0] * sweep.powf(t / params.duration);
+        let previous = phase;
+        phase += frequency / SAMPLE_RATE as f64;
+
+        let position = phase.fract();
+        let value = match params.waveform {
+            Waveform::Sine => (2.0 * f64::consts::PI * position).sin(),
+            Waveform::Square => if position < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sawtooth => 2.0 * position - 1.0,
+            Waveform::Noise => {
+                if phase.floor() != previous.floor() || i == 0 {
+                    noise = rng.gen_range(-1.0, 1.0);
+                }
+                noise
+            }
+        };
+
+        // Apply the envelope
+        let mut envelope = 1.0;
+        if t < params.attack {
+            envelope = t / params.attack;
+        }
+        let remaining = params.duration - t;
+        if remaining < params.release {
+            envelope = envelope.min(remaining / params.release);
+        }
+
+        output.push((value * envelope * params.volume * i16::MAX as f64) as i16);
+    }
+
+    output
+}
+
+/// Renders the background music, which is meant to be played in a loop
+pub fn render_music() -> Vec<i16> {
+    let mut output = vec![];
+    for &frequency in MELODY.iter() {
+        output.extend(render(&Params {
+            waveform: Waveform::Square,
+            frequency: [frequency, frequency],
+            duration: NOTE,
+            attack: 0.01,
+            release: 0.2,
+            volume: 0.12
+        }));
+    }
+    output
+}
+
+/// Generates every sound effect and the music, and writes them as WAV files in the given directory
+///
+/// Files that already hold the right sound are left alone, so the sounds are
+/// only written again after they change
+pub fn export<P: AsRef<Path>>(directory: P) -> Result<(), String> {
+    let directory = directory.as_ref();
+    try!(fs::create_dir_all(directory)
+             .map_err(|e| format!("Unable to create {}: {}", directory.display(), e)));
+
+    for sound in Sound::all() {
+        let samples = render(&sound.params());
+        try!(wav::write(directory.join(sound.file_name()), &samples, SAMPLE_RATE));
+    }
+    try!(wav::write(directory.join(super::MUSIC), &render_music(), SAMPLE_RATE));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use audio::{wav, Sound};
+    use super::{render, SAMPLE_RATE};
+
+    /// Reads a little endian value from the bytes
+    fn read_u32(bytes: &[u8]) -> u32 {
+        bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+    }
+
+    /// A rendered sound lasts as long as requested and is encoded behind a valid WAV header
+    #[test]
+    fn render_wav() {
+        let params = Sound::Shoot.params();
+        let samples = render(&params);
+        assert_eq!(samples.len(), (params.duration * SAMPLE_RATE as f64) as usize);
+        assert!(samples.iter().any(|&s| s != 0));
+
+        let bytes = wav::encode(&samples, SAMPLE_RATE);
+        assert_eq!(bytes.len(), 44 + samples.len() * 2);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(read_u32(&bytes[4..]) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(read_u32(&bytes[24..]), SAMPLE_RATE);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(read_u32(&bytes[40..]) as usize, samples.len() * 2);
+        assert_eq!(bytes[44] as u16 | (bytes[45] as u16) << 8, samples[0] as u16);
+    }
+}
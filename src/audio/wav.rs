@@ -0,0 +1,60 @@
+//! Writing of WAV files
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Returns the contents of a WAV file with the 16 bits mono samples
+pub fn encode(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+
+    // RIFF header
+    bytes.extend(b"RIFF".iter().cloned());
+    push_u32(&mut bytes, 36 + data_size);
+    bytes.extend(b"WAVE".iter().cloned());
+
+    // Format chunk: uncompressed PCM, 1 channel, 16 bits per sample
+    bytes.extend(b"fmt ".iter().cloned());
+    push_u32(&mut bytes, 16);
+    push_u16(&mut bytes, 1);
+    push_u16(&mut bytes, 1);
+    push_u32(&mut bytes, sample_rate);
+    push_u32(&mut bytes, sample_rate * 2);
+    push_u16(&mut bytes, 2);
+    push_u16(&mut bytes, 16);
+
+    // Data chunk
+    bytes.extend(b"data".iter().cloned());
+    push_u32(&mut bytes, data_size);
+    for &sample in samples {
+        push_u16(&mut bytes, sample as u16);
+    }
+    bytes
+}
+
+/// Writes the 16 bits mono samples to a WAV file, unless the file has them already
+pub fn write<P: AsRef<Path>>(path: P, samples: &[i16], sample_rate: u32) -> Result<(), String> {
+    let bytes = encode(samples, sample_rate);
+
+    let path = path.as_ref();
+    let mut existing = vec![];
+    if File::open(path).and_then(|mut f| f.read_to_end(&mut existing)).is_ok() && existing == bytes {
+        return Ok(());
+    }
+
+    File::create(path).and_then(|mut f| f.write_all(&bytes))
+                      .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+}
+
+/// Appends the value in little endian
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.push(value as u8);
+    bytes.push((value >> 8) as u8);
+}
+
+/// Appends the value in little endian
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    push_u16(bytes, value as u16);
+    push_u16(bytes, (value >> 16) as u16);
+}
@@ -0,0 +1,108 @@
+//! A scripted pilot that plays the game on its own
+//!
+//! The autopilot looks at the world and decides the actions of a player the
+//! way a simple-minded person would: it turns towards the nearest threat and
+//! shoots once it's aligned, turns away from threats that get too close, and
+//! boosts out of trouble when surrounded. It plays the demo on the title
+//! screen and serves as a baseline when balancing the game.
+
+use std::f64::consts::PI;
+
+use drawing::Point;
+use game::Actions;
+use models::World;
+use traits::{Advance, Position};
+
+/// Threats closer than this are avoided instead of attacked, in pixels
+const SAFE_DISTANCE: f64 = 120.0;
+
+/// The autopilot shoots when the nearest threat is within this angle of its direction
+const AIM_TOLERANCE: f64 = 0.1;
+
+/// The autopilot doesn't bother to rotate for differences smaller than this angle
+const TURN_TOLERANCE: f64 = 0.05;
+
+/// The radius within which threats are counted to decide if the autopilot is surrounded
+const DANGER_RADIUS: f64 = 250.0;
+
+/// The amount of threats within `DANGER_RADIUS` that make the autopilot boost away
+const SURROUNDED: usize = 3;
+
+/// Returns the actions the autopilot takes for the given player
+///
+/// Enemies and, in versus mode, the other players are threats. Bullets fired
+/// by others only count when deciding whether the player is surrounded
+pub fn actions(world: &World, player: usize) -> Actions {
+    let me = &world.players[player];
+    if !me.is_alive() {
+        return Actions::default();
+    }
+
+    let position = me.position();
+    let offsets = |points: Vec<Point>| -> Vec<Point> {
+        points.iter().map(|p| world.size.offset(&position, p)).collect()
+    };
+    let threats = offsets(world.enemies.iter().map(|e| e.position())
+                               .chain(world.players.iter().enumerate()
+                                          .filter(|&(i, p)| i != player && p.is_alive())
+                                          .map(|(_, p)| p.position()))
+                               .collect());
+    let bullets = offsets(world.bullets.iter().filter(|b| b.owner != player).map(|b| b.position()).collect());
+
+    let mut nearest: Option<&Point> = None;
+    for offset in &threats {
+        if nearest.map_or(true, |n| length(offset) < length(n)) {
+            nearest = Some(offset);
+        }
+    }
+    let nearest = match nearest {
+        Some(nearest) => nearest,
+        None => return Actions::default()
+    };
+
+    let aim = difference(nearest.y.atan2(nearest.x), me.direction());
+    let too_close = length(nearest) < SAFE_DISTANCE;
+    let around = threats.iter().chain(&bullets).filter(|o| length(o) < DANGER_RADIUS).count();
+
+    // Head towards the threat, or away from it when it's too close
+    let turn = if too_close { difference(aim + PI, 0.0) } else { aim };
+    Actions {
+        rotate_left: turn < -TURN_TOLERANCE,
+        rotate_right: turn > TURN_TOLERANCE,
+        boost: too_close || around >= SURROUNDED,
+        shoot: aim.abs() < AIM_TOLERANCE
+    }
+}
+
+fn length(offset: &Point) -> f64 {
+    (offset.x * offset.x + offset.y * offset.y).sqrt()
+}
+
+/// Returns the difference between two angles, between -π and π
+fn difference(a: f64, b: f64) -> f64 {
+    let d = (a - b) % (2.0 * PI);
+    if d > PI { d - 2.0 * PI } else if d < -PI { d + 2.0 * PI } else { d }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::Size;
+    use game::{Game, Mode, ScaleMode};
+
+    /// Plays a minute of a game with the autopilot and checks that it shoots
+    /// down enemies
+    #[test]
+    fn scores() {
+        let mut game = Game::new(Size::new(480.0, 300.0), ScaleMode::Letterbox, Mode::Single, 7).unwrap();
+        let mut score = 0;
+        for _ in 0..3600 {
+            let before = game.world().players[0].score;
+            let actions = super::actions(game.world(), 0);
+            game.set_actions(0, actions);
+            game.update(1.0 / 60.0);
+            // The score goes back to zero when the game starts again
+            score += game.world().players[0].score.saturating_sub(before);
+        }
+        assert!(score > 0);
+    }
+}
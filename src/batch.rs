@@ -0,0 +1,256 @@
+//! Runs many games without a window to gather statistics for balancing
+//!
+//! Each game is played from its own seed, either by the autopilot or with
+//! the inputs of a replay, until every player is out of lives, the versus
+//! round is over or the time is up. Games are spread over several threads,
+//! and the statistics of every player in every game are collected in a
+//! `Record`. Since games are deterministic, the same settings always give
+//! the same records, so the effect of a change in the game can be measured
+//! by running the same batch before and after it.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rustc_serialize::json;
+
+use autopilot;
+use game::{Cause, Event, Game, Mode, ScaleMode};
+use level::Level;
+use models::player;
+use replay::Replay;
+
+/// Where the input of the games comes from
+pub enum Pilot {
+    /// Every player is flown by the autopilot
+    Autopilot,
+    /// The recorded inputs are replayed, whatever the seed. Games end when the inputs do
+    Replay(Replay)
+}
+
+/// What to play
+pub struct Settings {
+    pub games: usize,
+    /// The seed of the first game. The following games use the following seeds
+    pub seed: u64,
+    pub mode: Mode,
+    pub ups: u64,
+    /// The maximum length of a game, in seconds
+    pub duration: f64,
+    pub threads: usize,
+    pub pilot: Pilot,
+    /// The level the games are played in, if any
+    pub level: Option<Level>
+}
+
+/// The statistics of a player in a game
+#[derive(Clone, RustcEncodable)]
+pub struct Record {
+    pub seed: u64,
+    /// The number of the player, starting at 1
+    pub player: usize,
+    /// The seconds until the player ran out of lives (or the whole game, if it didn't)
+    pub survival: f64,
+    pub score: u32,
+    /// The enemies (or, in versus mode, the players) shot down
+    pub kills: u32,
+    pub shots: u32,
+    pub deaths: u32,
+    /// The cause of the last death, if any: `enemy` or the number of the
+    /// player that fired the bullet, like `player 2`
+    pub cause_of_death: Option<String>
+}
+
+/// Plays the games and returns the records of every player, sorted by seed and player
+pub fn run(settings: Settings) -> Result<Vec<Record>, String> {
+    let settings = Arc::new(settings);
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..settings.threads {
+        let (settings, next, sender) = (settings.clone(), next.clone(), sender.clone());
+        thread::spawn(move || {
+            loop {
+                let game = next.fetch_add(1, Ordering::SeqCst);
+                if game >= settings.games {
+                    break;
+                }
+                let records = play(&settings, settings.seed.wrapping_add(game as u64));
+                let failed = records.is_err();
+                if sender.send(records).is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut records = vec![];
+    for game in receiver.iter() {
+        records.extend(try!(game));
+    }
+    records.sort_by(|a, b| (a.seed, a.player).cmp(&(b.seed, b.player)));
+    Ok(records)
+}
+
+/// Plays a game with the given seed and returns the records of its players
+fn play(settings: &Settings, seed: u64) -> Result<Vec<Record>, String> {
+    let mode = settings.mode;
+    let mut game = try!(Game::new(::resolution(), ScaleMode::Letterbox, mode, seed));
+    game.disable_rewind();
+    if let Some(ref level) = settings.level {
+        game.set_level(level);
+    }
+
+    let mut records: Vec<_> = (0..mode.players()).map(|player| Record {
+        seed: seed,
+        player: player + 1,
+        survival: 0.0,
+        score: 0,
+        kills: 0,
+        shots: 0,
+        deaths: 0,
+        cause_of_death: None
+    }).collect();
+
+    let dt = 1.0 / settings.ups as f64;
+    let mut scores = vec![0; mode.players()];
+    let mut time = 0.0;
+    for update in 0..(settings.duration * settings.ups as f64) as usize {
+        match settings.pilot {
+            Pilot::Autopilot => for player in 0..mode.players() {
+                let actions = autopilot::actions(game.world(), player);
+                game.set_actions(player, actions);
+            },
+            Pilot::Replay(ref replay) if update < replay.inputs.len() => replay.apply(&mut game, update),
+            Pilot::Replay(_) => break
+        }
+
+        game.update(dt);
+        time += dt;
+
+        for event in game.events() {
+            match *event {
+                Event::Shot(i) => records[i].shots += 1,
+                Event::Kill(i, _) => records[i].kills += 1,
+                Event::Death(i, cause) => {
+                    let record = &mut records[i];
+                    record.deaths += 1;
+                    record.cause_of_death = Some(match cause {
+                        Cause::Enemy => "enemy".to_string(),
+                        Cause::Player(killer) => format!("player {}", killer + 1),
+                        Cause::Hazard => "hazard".to_string()
+                    });
+                    if !mode.is_versus() && record.deaths == player::LIVES {
+                        record.survival = time;
+                    }
+                }
+            }
+        }
+
+        let over = if mode.is_versus() {
+            game.is_round_over()
+        } else {
+            records.iter().all(|r| r.deaths >= player::LIVES)
+        };
+        // When the last player runs out of lives the game starts again right
+        // away, so the scores are the ones from before the last update
+        if over {
+            break;
+        }
+        scores = game.world().players.iter().map(|p| p.score).collect();
+    }
+
+    for (record, &score) in records.iter_mut().zip(&scores) {
+        record.score = score;
+        if mode.is_versus() || record.deaths < player::LIVES {
+            record.survival = time;
+        }
+    }
+    Ok(records)
+}
+
+/// Writes the records to a file, as JSON if its name ends with `.json` and as CSV otherwise
+pub fn save<P: AsRef<Path>>(records: &[Record], path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    let contents = if path.extension().map_or(false, |e| e == "json") {
+        try!(json::encode(&records).map_err(|e| format!("Unable to encode the records: {}", e)))
+    } else {
+        let mut csv = "seed,player,survival,score,kills,shots,deaths,cause_of_death\n".to_string();
+        for r in records {
+            csv.push_str(&format!("{},{},{:.3},{},{},{},{},{}\n", r.seed, r.player, r.survival, r.score,
+                                  r.kills, r.shots, r.deaths, r.cause_of_death.as_ref().map_or("", |c| &c[..])));
+        }
+        csv
+    };
+    File::create(path).and_then(|mut f| f.write_all(contents.as_bytes()))
+                      .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+}
+
+/// Returns the averages of the records and how often each cause of death was the last one
+pub fn summary(records: &[Record]) -> String {
+    if records.is_empty() {
+        return "No games were played".to_string();
+    }
+
+    let n = records.len() as f64;
+    let average = |f: &Fn(&Record) -> f64| records.iter().map(|r| f(r)).fold(0.0, |a, b| a + b) / n;
+    let mut summary = format!("Averages over {} players\n\
+                               Survival: {:.1} s\n\
+                               Score: {:.1}\n\
+                               Kills: {:.1}\n\
+                               Shots: {:.1}\n\
+                               Deaths: {:.2}\n\
+                               Last cause of death:",
+                              records.len(), average(&|r| r.survival), average(&|r| r.score as f64),
+                              average(&|r| r.kills as f64), average(&|r| r.shots as f64),
+                              average(&|r| r.deaths as f64));
+
+    let mut causes: Vec<(&str, usize)> = vec![];
+    for record in records {
+        let cause = record.cause_of_death.as_ref().map_or("none", |c| &c[..]);
+        match causes.iter().position(|&(c, _)| c == cause) {
+            Some(i) => causes[i].1 += 1,
+            None => causes.push((cause, 1))
+        }
+    }
+    causes.sort();
+    for (cause, count) in causes {
+        summary.push_str(&format!(" {} {:.0}%", cause, count as f64 / n * 100.0));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use game::Mode;
+    use super::{Pilot, Settings};
+
+    /// Runs the same batch with one and several threads, which must give the same records
+    #[test]
+    fn deterministic() {
+        let settings = |threads| Settings {
+            games: 4,
+            seed: 20,
+            mode: Mode::Coop,
+            ups: 60,
+            duration: 20.0,
+            threads: threads,
+            pilot: Pilot::Autopilot,
+            level: None
+        };
+        let (a, b) = (super::run(settings(1)).unwrap(), super::run(settings(3)).unwrap());
+
+        assert_eq!(a.len(), 8);
+        assert_eq!(a.iter().map(|r| r.seed).collect::<Vec<_>>(), vec![20, 20, 21, 21, 22, 22, 23, 23]);
+        for (a, b) in a.iter().zip(&b) {
+            assert_eq!((a.survival, a.score, a.kills, a.shots, a.deaths), (b.survival, b.score, b.kills, b.shots, b.deaths));
+            assert_eq!(a.cause_of_death, b.cause_of_death);
+        }
+        assert!(a.iter().any(|r| r.shots > 0));
+    }
+}
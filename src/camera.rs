@@ -0,0 +1,122 @@
+//! The camera determines which part of the world is visible on the screen
+
+use graphics::{Context, Transformed};
+use rand::Rng;
+
+use drawing::{Point, Size};
+
+/// How fast the camera catches up with its target (higher is faster)
+const FOLLOW_RATE: f64 = 5.0;
+/// How fast the zoom reaches its target (higher is faster)
+const ZOOM_RATE: f64 = 3.0;
+/// The amount of trauma that wears off every second
+const TRAUMA_DECAY: f64 = 1.2;
+/// The offset of the view, in pixels, at maximum trauma
+const MAX_SHAKE_OFFSET: f64 = 20.0;
+/// The rotation of the view, in radians, at maximum trauma
+const MAX_SHAKE_ANGLE: f64 = 0.05;
+
+/// A camera that follows a target around the world
+///
+/// The camera supports screen shake, based on the amount of "trauma" it has
+/// received: explosions add trauma, which wears off over time. The shake is
+/// proportional to the square of the trauma, so small hits are subtle and
+/// big ones are violent
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Camera {
+    /// The point of the world shown at the center of the screen
+    pub center: Point,
+    /// The size of the screen
+    pub viewport: Size,
+    /// The current zoom (1 means no zoom)
+    pub zoom: f64,
+    /// The zoom the camera is moving towards
+    target_zoom: f64,
+    /// The amount of trauma, between 0 and 1
+    trauma: f64,
+    /// The current shake offset and rotation
+    shake: (Point, f64)
+}
+
+impl Camera {
+    /// Returns a camera for a screen of the given size, centered on `center`
+    pub fn new(viewport: Size, center: Point) -> Camera {
+        Camera {
+            center: center,
+            viewport: viewport,
+            zoom: 1.0,
+            target_zoom: 1.0,
+            trauma: 0.0,
+            shake: (Point::new(0.0, 0.0), 0.0)
+        }
+    }
+
+    /// Adds trauma to the camera, causing it to shake
+    pub fn add_trauma(&mut self, amount: f64) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// Sets the zoom the camera will smoothly move to
+    pub fn zoom_to(&mut self, zoom: f64) {
+        self.target_zoom = zoom;
+    }
+
+    /// Returns the size of the region of the world that is visible
+    pub fn visible_size(&self) -> Size {
+        Size::new(self.viewport.width / self.zoom, self.viewport.height / self.zoom)
+    }
+
+    /// Updates the camera so it follows the target without leaving the world
+    pub fn update<R: Rng>(&mut self, dt: f64, target: Point, world: &Size, rng: &mut R) {
+        // Approach the target zoom exponentially, so it is independent of the framerate
+        self.zoom += (self.target_zoom - self.zoom) * (1.0 - (-ZOOM_RATE * dt).exp());
+
+        // When the target wraps around the world, jump instead of panning across it
+        if (target.x - self.center.x).abs() > world.width / 2.0
+            || (target.y - self.center.y).abs() > world.height / 2.0 {
+            self.center = target;
+        } else {
+            let t = 1.0 - (-FOLLOW_RATE * dt).exp();
+            self.center.x += (target.x - self.center.x) * t;
+            self.center.y += (target.y - self.center.y) * t;
+        }
+
+        // Keep the visible region inside the world
+        let visible = self.visible_size();
+        self.center.x = clamp(self.center.x, visible.width / 2.0, world.width - visible.width / 2.0);
+        self.center.y = clamp(self.center.y, visible.height / 2.0, world.height - visible.height / 2.0);
+
+        // Shake the camera
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        let shake = self.trauma * self.trauma;
+        self.shake = (Point::new(MAX_SHAKE_OFFSET * shake * rng.gen_range(-1.0, 1.0),
+                                 MAX_SHAKE_OFFSET * shake * rng.gen_range(-1.0, 1.0)),
+                      MAX_SHAKE_ANGLE * shake * rng.gen_range(-1.0, 1.0));
+    }
+
+    /// Returns the point of the world shown at the given point of the screen,
+    /// leaving the shake out
+    pub fn to_world(&self, point: &Point) -> Point {
+        Point::new(self.center.x + (point.x - self.viewport.width / 2.0) / self.zoom,
+                   self.center.y + (point.y - self.viewport.height / 2.0) / self.zoom)
+    }
+
+    /// Returns a context whose transform maps world coordinates to the screen
+    pub fn apply(&self, c: Context) -> Context {
+        let (ref offset, angle) = self.shake;
+        c.trans(self.viewport.width / 2.0 + offset.x, self.viewport.height / 2.0 + offset.y)
+         .rot_rad(angle)
+         .zoom(self.zoom)
+         .trans(-self.center.x, -self.center.y)
+    }
+}
+
+/// Restricts the value to the given range. If the range is empty (the world
+/// is smaller than the screen), the middle of the range is returned
+fn clamp(value: f64, min: f64, max: f64) -> f64 {
+    if min > max {
+        (min + max) / 2.0
+    } else {
+        value.max(min).min(max)
+    }
+}
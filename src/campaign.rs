@@ -0,0 +1,364 @@
+//! The campaign, a sequence of levels with an objective each
+//!
+//! The levels are described in `resources/campaign.toml`: the size of the
+//! arena, its layout (a level file, as made with the level editor), the waves
+//! of enemies and the objective. Completing a level unlocks the next one and
+//! earns one star, plus one more for each score threshold reached. The best
+//! stars of each level are kept in a progress file between sessions.
+
+use std::cmp;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+
+use graphics::{self, Transformed};
+use rustc_serialize::Decodable;
+use toml;
+
+use drawing::{color, Size};
+use game::{render_centered, Event, Game};
+use level::Level;
+use models::player;
+use models::enemy::Kind;
+use renderer::Renderer;
+use waves::Waves;
+
+/// The levels of the campaign, in order
+pub struct Campaign {
+    pub stages: Vec<Stage>
+}
+
+/// A level of the campaign
+pub struct Stage {
+    pub name: String,
+    /// The size of the arena
+    pub size: Size,
+    pub level: Level,
+    pub waves: Waves,
+    pub objective: Objective,
+    /// The scores needed for the second and the third star
+    pub stars: (u32, u32)
+}
+
+/// What the players must do to complete a level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Objective {
+    /// Stay alive for the given amount of seconds
+    Survive(f64),
+    /// Shoot down the given amount of enemies
+    Kill(u32),
+    /// Shoot down the boss
+    Boss
+}
+
+/// How an attempt at a level ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    /// The objective was met, earning the given amount of stars
+    Complete(u32),
+    /// Every player ran out of lives
+    Failed
+}
+
+/// The contents of the campaign file
+#[derive(RustcDecodable)]
+struct Source {
+    level: Vec<StageSource>
+}
+
+#[derive(RustcDecodable)]
+struct StageSource {
+    name: String,
+    width: f64,
+    height: f64,
+    layout: Option<String>,
+    waves: String,
+    objective: String,
+    target: Option<f64>,
+    stars: Vec<u32>
+}
+
+impl Campaign {
+    /// Loads the campaign described in the given file, along with the layouts
+    /// and waves of its levels
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Campaign, String> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+                 .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+        let source = try!(parse(&source).map_err(|e| format!("Invalid campaign {}: {}", path.display(), e)));
+
+        let mut stages = vec![];
+        for stage in source.level {
+            let layout = match stage.layout {
+                Some(ref path) => try!(Level::load(path)),
+                None => Level::default()
+            };
+            let objective = match (&stage.objective[..], stage.target) {
+                ("survive", Some(seconds)) => Objective::Survive(seconds),
+                ("kill", Some(enemies)) => Objective::Kill(enemies as u32),
+                ("boss", _) => Objective::Boss,
+                (objective, _) => {
+                    return Err(format!("Invalid campaign {}: the objective `{}` of {} needs a target",
+                                       path.display(), objective, stage.name));
+                }
+            };
+            if stage.stars.len() != 2 {
+                return Err(format!("Invalid campaign {}: {} needs two scores for the stars", path.display(), stage.name));
+            }
+            stages.push(Stage {
+                size: Size::new(stage.width, stage.height),
+                level: layout,
+                waves: try!(Waves::load(&stage.waves)),
+                objective: objective,
+                stars: (stage.stars[0], stage.stars[1]),
+                name: stage.name
+            });
+        }
+        Ok(Campaign { stages: stages })
+    }
+}
+
+/// Returns the contents of a campaign file
+fn parse(source: &str) -> Result<Source, String> {
+    let mut parser = toml::Parser::new(source);
+    match parser.parse() {
+        Some(table) => Source::decode(&mut toml::Decoder::new(toml::Value::Table(table))).map_err(|e| e.to_string()),
+        None => {
+            let errors: Vec<_> = parser.errors.iter().map(|e| e.desc.clone()).collect();
+            Err(errors.join(", "))
+        }
+    }
+}
+
+impl Stage {
+    /// Sets up the game to play this level, from the start
+    pub fn start(&self, game: &mut Game) {
+        game.set_size(self.size.clone());
+        game.set_waves(self.waves.clone());
+        game.set_level(&self.level);
+    }
+
+    /// Returns the stars earned by completing the level with the given score
+    pub fn stars(&self, score: u32) -> u32 {
+        1 + (score >= self.stars.0) as u32 + (score >= self.stars.1) as u32
+    }
+}
+
+impl Objective {
+    /// Returns the objective as shown to the players
+    pub fn describe(&self) -> String {
+        match *self {
+            Objective::Survive(seconds) => format!("Survive for {} seconds", seconds),
+            Objective::Kill(enemies) => format!("Shoot down {} enemies", enemies),
+            Objective::Boss => "Defeat the boss".to_string()
+        }
+    }
+}
+
+/// An attempt at a level of the campaign, following the game to tell when it's over
+pub struct Attempt {
+    /// The index of the level
+    pub stage: usize,
+    /// The time of the game when the attempt started
+    start: f64,
+    /// The seconds the players have survived
+    elapsed: f64,
+    /// The amount of enemies shot down
+    kills: u32,
+    /// The amount of lives each player has lost
+    deaths: Vec<u32>,
+    /// True once a boss has been shot down
+    boss_defeated: bool,
+    pub outcome: Option<Outcome>
+}
+
+impl Attempt {
+    /// Starts an attempt at the given level, which has just been set up in the game
+    pub fn new(stage: usize, game: &Game) -> Attempt {
+        Attempt {
+            stage: stage,
+            start: game.world().time,
+            elapsed: 0.0,
+            kills: 0,
+            deaths: vec![0; game.world().players.len()],
+            boss_defeated: false,
+            outcome: None
+        }
+    }
+
+    /// Follows the game after an update, deciding the outcome once the
+    /// objective is met or every player is out of lives
+    pub fn update(&mut self, game: &Game, stage: &Stage) -> Option<Outcome> {
+        if self.outcome.is_some() {
+            return self.outcome;
+        }
+
+        for event in game.events() {
+            match *event {
+                Event::Kill(_, kind) => {
+                    self.kills += 1;
+                    self.boss_defeated |= kind == Some(Kind::Boss);
+                }
+                Event::Death(i, _) => self.deaths[i] += 1,
+                Event::Shot(_) => {}
+            }
+        }
+        // The game starts again on its own once everyone is out of lives, so
+        // the lost lives are counted instead of looking at the players
+        if self.deaths.iter().all(|&deaths| deaths >= player::LIVES) {
+            self.outcome = Some(Outcome::Failed);
+            return self.outcome;
+        }
+
+        let world = game.world();
+        self.elapsed = world.time - self.start;
+        let met = match stage.objective {
+            Objective::Survive(seconds) => self.elapsed >= seconds,
+            Objective::Kill(enemies) => self.kills >= enemies,
+            Objective::Boss => self.boss_defeated
+        };
+        if met {
+            let score = world.players.iter().map(|p| p.score).fold(0, |a, b| a + b);
+            self.outcome = Some(Outcome::Complete(stage.stars(score)));
+        }
+        self.outcome
+    }
+
+    /// Renders the progress towards the objective at the top of the screen
+    /// or, once the attempt is over, its outcome in the middle of it
+    pub fn render<R: Renderer>(&self, c: graphics::context::Context, r: &mut R, stage: &Stage, screen: &Size) {
+        let (x, y) = (screen.width / 2.0, screen.height / 2.0);
+        let outcome = match self.outcome {
+            Some(outcome) => outcome,
+            None => {
+                let text = match stage.objective {
+                    Objective::Survive(seconds) => {
+                        let left = (seconds - self.elapsed).max(0.0).ceil() as u32;
+                        format!("Survive {}:{:02}", left / 60, left % 60)
+                    }
+                    Objective::Kill(enemies) => format!("Kills {}/{}", cmp::min(self.kills, enemies), enemies),
+                    Objective::Boss => "Defeat the boss".to_string()
+                };
+                render_centered(r, color::ORANGE, 22, &text, c.trans(x, 20.0));
+                return;
+            }
+        };
+
+        let (title, detail) = match outcome {
+            Outcome::Complete(stars) => ("Level complete!".to_string(), stars_text(stars)),
+            Outcome::Failed => ("Level failed".to_string(), stage.objective.describe())
+        };
+        r.rectangle([0.0, 0.0, 0.0, 0.6], [0.0, y - 70.0, screen.width, 120.0], c.transform);
+        render_centered(r, color::ORANGE, 40, &title, c.trans(x, y - 30.0));
+        render_centered(r, color::ORANGE, 22, &detail, c.trans(x, y + 10.0));
+        render_centered(r, color::ORANGE, 16, "Press Enter to continue", c.trans(x, y + 38.0));
+    }
+}
+
+/// Returns a row of three stars, with the given amount of them filled
+pub fn stars_text(stars: u32) -> String {
+    (0..3).map(|i| if i < stars { "*" } else { "-" }).collect::<Vec<_>>().join(" ")
+}
+
+/// The stars earned in each level of the campaign, kept between sessions
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
+pub struct Progress {
+    /// The best amount of stars of each level, in order. Levels that were
+    /// never completed have none
+    pub stars: Vec<u32>
+}
+
+impl Progress {
+    /// Loads the progress stored in the given file. Without a file, nothing
+    /// has been completed yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Progress, String> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        match File::open(path).and_then(|mut f| f.read_to_string(&mut source)) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Progress::default()),
+            Err(e) => return Err(format!("Unable to read {}: {}", path.display(), e))
+        }
+
+        let mut parser = toml::Parser::new(&source);
+        let table = try!(parser.parse().ok_or(format!("Invalid progress file {}", path.display())));
+        Progress::decode(&mut toml::Decoder::new(toml::Value::Table(table)))
+            .map_err(|e| format!("Invalid progress file {}: {}", path.display(), e))
+    }
+
+    /// Saves the progress to the given file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        File::create(path).and_then(|mut f| f.write_all(toml::encode_str(self).as_bytes()))
+                          .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+
+    /// Returns the best amount of stars earned in the given level
+    pub fn stars(&self, stage: usize) -> u32 {
+        self.stars.get(stage).cloned().unwrap_or(0)
+    }
+
+    /// Returns true if the given level can be played: the first one always
+    /// can, and the rest once the previous one is completed
+    pub fn is_unlocked(&self, stage: usize) -> bool {
+        stage == 0 || self.stars(stage - 1) > 0
+    }
+
+    /// Records that the given level was completed with the given amount of
+    /// stars, unless it was already completed with more
+    pub fn record(&mut self, stage: usize, stars: u32) {
+        while self.stars.len() <= stage {
+            self.stars.push(0);
+        }
+        self.stars[stage] = cmp::max(self.stars[stage], stars);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::Size;
+    use game::{Game, Mode, ScaleMode};
+    use super::{Attempt, Campaign, Objective, Outcome, Progress};
+
+    /// Loads the campaign of the game, plays its first level without touching
+    /// the keys until it's decided, and checks how the progress changes
+    #[test]
+    fn first_level() {
+        let campaign = Campaign::load("resources/campaign.toml").unwrap();
+        let stage = &campaign.stages[0];
+        match stage.objective {
+            Objective::Survive(_) => {}
+            objective => panic!("the first level should be about surviving, not {:?}", objective)
+        }
+
+        let mut game = Game::new(Size::new(1024.0, 600.0), ScaleMode::Letterbox, Mode::Single, 4).unwrap();
+        stage.start(&mut game);
+        assert_eq!(game.world().size.width, stage.size.width);
+
+        let mut attempt = Attempt::new(0, &game);
+        let mut outcome = None;
+        for _ in 0..60 * 600 {
+            game.update(1.0 / 60.0);
+            outcome = attempt.update(&game, stage);
+            if outcome.is_some() {
+                break;
+            }
+        }
+
+        // Nobody flies the ship, so it is destroyed before the time is up
+        match outcome {
+            Some(Outcome::Failed) => {}
+            Some(Outcome::Complete(stars)) => panic!("the level was completed with {} stars", stars),
+            None => panic!("the level never ended")
+        }
+
+        // Completing the level unlocks the next one, and a worse completion keeps the stars
+        let mut progress = Progress::default();
+        assert!(!progress.is_unlocked(1));
+        progress.record(0, 1);
+        progress.record(0, 0);
+        assert_eq!(progress.stars(0), 1);
+        assert!(progress.is_unlocked(1));
+    }
+}
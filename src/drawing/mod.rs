@@ -4,11 +4,29 @@ mod point;
 mod size;
 
 pub mod color {
+    /// A color in RGBA format, with each component between 0 and 1
+    pub type Color = [f32; 4];
+
     pub const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
     pub const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+    pub const CYAN: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+    pub const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+    pub const GREY: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
     pub const ORANGE: [f32; 4] = [1.0, 0.5, 0.0, 1.0];
+    pub const PINK: [f32; 4] = [1.0, 0.4, 0.7, 1.0];
     pub const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
     pub const VIOLET: [f32; 4] = [0.6, 0.0, 1.0, 1.0];
+    pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    pub const YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+    /// Linearly interpolates between two colors, where `t == 0` returns `from`
+    /// and `t == 1` returns `to`
+    pub fn lerp(from: Color, to: Color, t: f32) -> Color {
+        [from[0] + (to[0] - from[0]) * t,
+         from[1] + (to[1] - from[1]) * t,
+         from[2] + (to[2] - from[2]) * t,
+         from[3] + (to[3] - from[3]) * t]
+    }
 }
 
 pub use self::point::Point;
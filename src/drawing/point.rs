@@ -3,7 +3,7 @@ use rand::Rng;
 use super::Size;
 
 /// A `Point` represents a position in space
-#[derive(Clone, Default)]
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
 pub struct Point {
     pub x: f64,
     pub y: f64
@@ -29,6 +29,14 @@ impl Point {
         + (self.y - target.y) * (self.y - target.y)
     }
 
+    /// Returns the point of the segment from `a` to `b` closest to this one
+    pub fn closest_on_segment(&self, a: &Point, b: &Point) -> Point {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx * dx + dy * dy;
+        let t = if length == 0.0 { 0.0 } else { (((self.x - a.x) * dx + (self.y - a.y) * dy) / length).max(0.0).min(1.0) };
+        Point::new(a.x + dx * t, a.y + dy * t)
+    }
+
     /// Rotates the point through the origin in the given angle (radians)
     pub fn rotate(mut self, radians: f64) -> Point {
         let radius = (self.x * self.x + self.y * self.y).sqrt();
@@ -3,7 +3,7 @@ use rand::Rng;
 use super::Point;
 
 /// A `Size` represents a region in space
-#[derive(Clone, Default)]
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
 pub struct Size {
     pub width: f64,
     pub height: f64
@@ -21,6 +21,15 @@ impl Size {
         && 0.0 <= point.y && point.y <= self.height
     }
 
+    /// Returns the shortest offset from one point to another, in a world of
+    /// this size that wraps around
+    pub fn offset(&self, from: &Point, to: &Point) -> Point {
+        fn wrap(d: f64, bound: f64) -> f64 {
+            if d > bound / 2.0 { d - bound } else if d < -bound / 2.0 { d + bound } else { d }
+        }
+        Point::new(wrap(to.x - from.x, self.width), wrap(to.y - from.y, self.height))
+    }
+
     /// Returns a random x coordinate within the bounds of this `Size`
     pub fn random_x<R: Rng>(&self, rng: &mut R) -> f64 {
         rng.gen_range(0.0, self.width)
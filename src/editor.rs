@@ -0,0 +1,198 @@
+//! The level editor, where obstacles, spawn points and pickups are placed with the mouse
+//!
+//! While the editor is open, the game stands still and the whole world is
+//! shown. The left button places the selected kind of object under the mouse
+//! and the right button removes the object under it. The editor places round
+//! and square obstacles; other rectangles and polygons, as well as the
+//! hazards, can be added to the level file by hand.
+
+use graphics::{Context, Transformed};
+use piston::input::{Key, MouseButton};
+
+use drawing::{color, Point, Size};
+use game::{render_centered, Game};
+use level::Level;
+use models::{Obstacle, Pickup, Shape};
+use renderer::Renderer;
+use traits::Position;
+
+/// The radius of new obstacles (or half the side, for squares), until it is changed
+const DEFAULT_RADIUS: f64 = 40.0;
+
+/// The smallest and largest radius of obstacles, in pixels
+const RADIUS_RANGE: (f64, f64) = (10.0, 300.0);
+
+/// Objects closer than this to the mouse are removed by the right button, in pixels
+const GRAB_DISTANCE: f64 = 20.0;
+
+/// The shape used to show spawn points, which are invisible while playing
+const SPAWN_POINT: &'static [[f64; 2]] = &[
+    [0.0, -12.0],
+    [10.0, 8.0],
+    [-10.0, 8.0]
+];
+
+/// The kinds of objects the editor places
+#[derive(Clone, Copy, PartialEq)]
+enum Tool {
+    Circle,
+    Square,
+    SpawnPoint,
+    Pickup
+}
+
+impl Tool {
+    fn name(&self) -> &'static str {
+        match *self {
+            Tool::Circle => "round obstacle",
+            Tool::Square => "square obstacle",
+            Tool::SpawnPoint => "spawn point",
+            Tool::Pickup => "pickup"
+        }
+    }
+}
+
+/// The state of the editor
+pub struct Editor {
+    /// The file the level is saved to and loaded from
+    path: String,
+    tool: Tool,
+    /// The radius of new obstacles
+    radius: f64,
+    /// True if bullets bounce off new obstacles
+    ricochet: bool,
+    /// The position of the mouse in the window
+    cursor: Point
+}
+
+impl Editor {
+    /// Opens the editor on the given game, saving the level to the given file
+    pub fn new(path: &str, game: &mut Game) -> Editor {
+        show_world(game);
+        Editor {
+            path: path.to_string(),
+            tool: Tool::Circle,
+            radius: DEFAULT_RADIUS,
+            ricochet: false,
+            cursor: Point::new(0.0, 0.0)
+        }
+    }
+
+    /// Processes a key press
+    pub fn key_press(&mut self, key: Key, game: &mut Game) {
+        match key {
+            Key::D1 => self.tool = Tool::Circle,
+            Key::D2 => self.tool = Tool::Square,
+            Key::D3 => self.tool = Tool::SpawnPoint,
+            Key::D4 => self.tool = Tool::Pickup,
+            Key::R => self.ricochet = !self.ricochet,
+            Key::W => {
+                let world = game.edit();
+                world.walls = !world.walls;
+            }
+            Key::LeftBracket => self.radius = (self.radius - 10.0).max(RADIUS_RANGE.0),
+            Key::RightBracket => self.radius = (self.radius + 10.0).min(RADIUS_RANGE.1),
+            Key::S => match Level::from_world(game.world()).save(&self.path) {
+                Ok(()) => println!("Level saved to {}", self.path),
+                Err(e) => println!("{}", e)
+            },
+            Key::L => match Level::load(&self.path) {
+                Ok(level) => {
+                    game.set_level(&level);
+                    show_world(game);
+                }
+                Err(e) => println!("{}", e)
+            },
+            _ => {}
+        }
+    }
+
+    /// Processes a movement of the mouse to the given point of the window
+    pub fn mouse_move(&mut self, x: f64, y: f64) {
+        self.cursor = Point::new(x, y);
+    }
+
+    /// Processes a mouse click: the left button places an object and the
+    /// right one removes the object under the mouse
+    pub fn mouse_press(&mut self, button: MouseButton, game: &mut Game) {
+        let point = game.to_world(&self.cursor);
+        if !game.world().size.contains(point.clone()) {
+            return;
+        }
+
+        let world = game.edit();
+        match button {
+            MouseButton::Left => match self.tool {
+                Tool::Circle | Tool::Square => {
+                    let obstacle = Obstacle::new(point, self.shape(), self.ricochet);
+                    world.obstacles.push(obstacle);
+                }
+                Tool::SpawnPoint => world.spawn_points.push(point),
+                Tool::Pickup => world.pickups.push(Pickup::new(point))
+            },
+            MouseButton::Right => {
+                // The most recently placed object goes first
+                let near = |position: Point, reach: f64| position.squared_distance_to(&point) < reach * reach;
+                if let Some(i) = world.pickups.iter().rposition(|p| near(p.position(), GRAB_DISTANCE)) {
+                    world.pickups.remove(i);
+                } else if let Some(i) = world.spawn_points.iter().rposition(|p| near(p.clone(), GRAB_DISTANCE)) {
+                    world.spawn_points.remove(i);
+                } else if let Some(i) = world.obstacles.iter().rposition(|o| o.push_out(&point, 0.0).is_some()) {
+                    world.obstacles.remove(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Draws the spawn points, the object about to be placed and the
+    /// controls of the editor over the game
+    pub fn render<R: Renderer>(&self, c: Context, r: &mut R, game: &Game, screen: &Size) {
+        let view = game.world_context(c);
+        let faint = |mut color: [f32; 4]| { color[3] = 0.4; color };
+
+        for point in &game.world().spawn_points {
+            r.polygon(color::GREEN, SPAWN_POINT, view.transform.trans(point.x, point.y));
+        }
+
+        // Collected pickups are hidden while playing, but not while editing
+        for pickup in game.world().pickups.iter().filter(|p| !p.is_available()) {
+            pickup.draw(&view, r);
+        }
+
+        let point = game.to_world(&self.cursor);
+        match self.tool {
+            Tool::Circle | Tool::Square => Obstacle::new(point, self.shape(), self.ricochet).draw(&view, r),
+            Tool::SpawnPoint => r.polygon(faint(color::GREEN), SPAWN_POINT, view.transform.trans(point.x, point.y)),
+            Tool::Pickup => Pickup::new(point).draw(&view, r)
+        }
+
+        let placing = match self.tool {
+            Tool::Circle | Tool::Square => format!("Placing: {} (size {}{})", self.tool.name(), self.radius,
+                                                   if self.ricochet { ", bullets bounce off" } else { "" }),
+            _ => format!("Placing: {}", self.tool.name())
+        };
+        let walls = if game.world().walls { "on" } else { "off" };
+        let (x, y) = (screen.width / 2.0, screen.height - 40.0);
+        render_centered(r, color::ORANGE, 22, &placing, c.trans(x, y));
+        render_centered(r, color::ORANGE, 16,
+                        &format!("1-4 choose  [ ] resize  R ricochet  W walls ({})  S save  L load  F2 play", walls),
+                        c.trans(x, y + 24.0));
+    }
+
+    /// Returns the shape of the obstacles about to be placed
+    fn shape(&self) -> Shape {
+        match self.tool {
+            Tool::Square => Shape::Rectangle(Size::new(self.radius * 2.0, self.radius * 2.0)),
+            _ => Shape::Circle(self.radius)
+        }
+    }
+}
+
+/// Zooms the camera out to show the whole world
+fn show_world(game: &mut Game) {
+    let world = game.edit();
+    let camera = &mut world.camera;
+    camera.zoom = (camera.viewport.width / world.size.width).min(camera.viewport.height / world.size.height);
+    camera.center = Point::new(world.size.width / 2.0, world.size.height / 2.0);
+}
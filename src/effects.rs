@@ -0,0 +1,107 @@
+//! Visual effects described in a data file
+//!
+//! Every effect is an `Emitter` identified by its name. The definitions are
+//! loaded at startup from a TOML file, so the visuals can be tuned without
+//! recompiling. In debug builds the file is watched and reloaded when it changes
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use rustc_serialize::Decodable;
+use toml;
+
+use models::{Emitter, ParticlePool, Vector};
+
+/// The amount of seconds between checks for changes in the effects file
+const RELOAD_INTERVAL: f64 = 0.5;
+
+/// The effects available to the game, indexed by name
+#[derive(Clone)]
+pub struct Effects {
+    emitters: HashMap<String, Emitter>,
+    path: PathBuf,
+    /// The contents of the file when it was last loaded
+    source: String,
+    /// Seconds since the last check for changes
+    since_check: f64
+}
+
+impl Effects {
+    /// Loads the effects defined in the given file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Effects, String> {
+        let path = path.as_ref().to_path_buf();
+        let source = try!(read_file(&path));
+        let emitters = try!(parse(&source));
+        Ok(Effects {
+            emitters: emitters,
+            path: path,
+            source: source,
+            since_check: 0.0
+        })
+    }
+
+    /// Spawns the particles of the effect with the given name, using `origin`
+    /// as the center of the effect
+    ///
+    /// Unknown effects are ignored, so a typo in the data file doesn't crash the game
+    pub fn emit(&self, name: &str, particles: &mut ParticlePool, origin: Vector) {
+        if let Some(emitter) = self.emitters.get(name) {
+            emitter.emit(particles, origin);
+        }
+    }
+
+    /// Reloads the effects file if its contents have changed
+    ///
+    /// This only does something in debug builds. If the new contents are
+    /// invalid, the old effects are kept and the error is returned (only once
+    /// for each change of the file)
+    pub fn update(&mut self, dt: f64) -> Result<(), String> {
+        if !cfg!(debug_assertions) {
+            return Ok(());
+        }
+
+        self.since_check += dt;
+        if self.since_check < RELOAD_INTERVAL {
+            return Ok(());
+        }
+        self.since_check = 0.0;
+
+        let source = match read_file(&self.path) {
+            Ok(source) => source,
+            Err(_) => return Ok(())
+        };
+
+        if source == self.source {
+            return Ok(());
+        }
+        let emitters = parse(&source);
+        self.source = source;
+        self.emitters = try!(emitters);
+        Ok(())
+    }
+}
+
+/// Returns the contents of the file at the given path
+fn read_file(path: &Path) -> Result<String, String> {
+    let mut source = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+             .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+    Ok(source)
+}
+
+/// Parses the definitions of the effects
+fn parse(source: &str) -> Result<HashMap<String, Emitter>, String> {
+    let mut parser = toml::Parser::new(source);
+    let table = match parser.parse() {
+        Some(table) => table,
+        None => {
+            let errors: Vec<_> = parser.errors.iter().map(|e| e.desc.clone()).collect();
+            return Err(format!("Invalid effects file: {}", errors.join(", ")));
+        }
+    };
+
+    let mut decoder = toml::Decoder::new(toml::Value::Table(table));
+    Decodable::decode(&mut decoder).map_err(|e| format!("Invalid effects file: {}", e))
+}
@@ -0,0 +1,254 @@
+//! A reinforcement learning environment around the game
+//!
+//! The environment follows the usual interface of training libraries: `reset`
+//! starts a new game and returns what the agent sees, and `step` plays an
+//! update with the action chosen by the agent and returns what it sees next,
+//! the reward and whether the game is over. The agent controls the first
+//! player; any other player stays idle.
+//!
+//! Nothing is drawn and no window is needed, so games run as fast as the
+//! simulation allows (many thousands of steps per second).
+//!
+//! Observations are vectors of `OBSERVATION_SIZE` numbers:
+//!
+//! * The player: its position as a fraction of the size of the world, the
+//!   cosine and sine of its direction, whether it's invulnerable (1 or 0) and
+//!   its lives left.
+//! * The `NEAREST` enemies closest to the player, closest first: whether
+//!   there is such an enemy (1 or 0) and its offset from the player as a
+//!   fraction of the size of the world. The world wraps around, so the
+//!   offset is the shortest one.
+//! * The `NEAREST` bullets closest to the player, the same way as enemies,
+//!   followed by the cosine and sine of their direction and whether they were
+//!   fired by someone else (1 or 0).
+//!
+//! Missing enemies and bullets are all zeros. Actions are numbers between 0
+//! and 15, whose bits activate the actions of the player: 1 rotates left, 2
+//! rotates right, 4 boosts and 8 shoots. The reward is the score gained
+//! (10 points per enemy) plus `KILL_REWARD` per player shot down in versus
+//! mode, minus `DEATH_PENALTY` per life lost.
+
+use std::io::{BufRead, Write};
+
+use drawing::{Point, Size};
+use game::{Actions, Game, Mode, ScaleMode};
+use traits::{Advance, Position};
+
+/// The amount of updates per second of the simulation
+const UPS: f64 = 60.0;
+
+/// The amount of enemies and bullets included in the observations
+pub const NEAREST: usize = 8;
+
+/// The amount of numbers describing the player, each enemy and each bullet
+const PLAYER_SIZE: usize = 6;
+const ENEMY_SIZE: usize = 3;
+const BULLET_SIZE: usize = 6;
+
+/// The length of the observations
+pub const OBSERVATION_SIZE: usize = PLAYER_SIZE + NEAREST * (ENEMY_SIZE + BULLET_SIZE);
+
+/// The amount of different actions
+pub const ACTIONS: u8 = 16;
+
+/// The reward for shooting down another player in versus mode
+const KILL_REWARD: f64 = 10.0;
+
+/// The penalty for losing a life
+const DEATH_PENALTY: f64 = 50.0;
+
+/// A game played by an agent
+pub struct Environment {
+    game: Game,
+    mode: Mode
+}
+
+impl Environment {
+    /// Returns an environment for games of the given mode. A game must be
+    /// started with `reset` before stepping
+    pub fn new(mode: Mode) -> Result<Environment, String> {
+        let mut game = try!(Game::new(::resolution(), ScaleMode::Letterbox, mode, 0));
+        game.disable_rewind();
+        Ok(Environment { game: game, mode: mode })
+    }
+
+    /// Starts a new game with the given seed and returns the first observation
+    pub fn reset(&mut self, seed: u64) -> Result<Vec<f64>, String> {
+        self.game = try!(Game::new(::resolution(), ScaleMode::Letterbox, self.mode, seed));
+        self.game.disable_rewind();
+        Ok(self.observe())
+    }
+
+    /// Plays an update with the given action and returns the next observation,
+    /// the reward and whether the game is over
+    ///
+    /// Bits above the four actions are ignored. Once the game is over, the
+    /// next step starts a new one with the following seed of the game
+    pub fn step(&mut self, action: u8) -> (Vec<f64>, f64, bool) {
+        let before = self.game.world().players[0].clone();
+        self.game.set_actions(0, Actions {
+            rotate_left: action & 1 != 0,
+            rotate_right: action & 2 != 0,
+            boost: action & 4 != 0,
+            shoot: action & 8 != 0
+        });
+        self.game.update(1.0 / UPS);
+
+        let after = &self.game.world().players[0];
+        // When every player is out of lives (or a versus round is over) the
+        // game starts again on its own, which resets the counters of the players
+        let restarted = after.score < before.score || after.kills < before.kills || after.deaths < before.deaths;
+        let (reward, done) = if restarted {
+            (if self.mode.is_versus() { 0.0 } else { -DEATH_PENALTY }, true)
+        } else {
+            let deaths = (after.deaths - before.deaths) as f64;
+            let kills = (after.kills - before.kills) as f64;
+            ((after.score - before.score) as f64 + KILL_REWARD * kills - DEATH_PENALTY * deaths,
+             self.mode.is_versus() && self.game.is_round_over())
+        };
+
+        (self.observe(), reward, done)
+    }
+
+    /// Returns what the agent sees of the game, as described in the module documentation
+    pub fn observe(&self) -> Vec<f64> {
+        let world = self.game.world();
+        let size = &world.size;
+        let player = &world.players[0];
+        let position = player.position();
+
+        let mut observation = Vec::with_capacity(OBSERVATION_SIZE);
+        observation.extend(&[position.x / size.width, position.y / size.height,
+                             player.direction().cos(), player.direction().sin(),
+                             flag(player.invulnerable > 0.0), player.lives as f64]);
+
+        let enemies = nearest(world.enemies.iter().map(|e| offset(&position, &e.position(), size)));
+        for i in 0..NEAREST {
+            match enemies.get(i) {
+                Some(&(_, ref offset)) => observation.extend(&[1.0, offset.x, offset.y]),
+                None => observation.extend(&[0.0; ENEMY_SIZE])
+            }
+        }
+
+        let bullets = nearest(world.bullets.iter().map(|b| offset(&position, &b.position(), size)));
+        for i in 0..NEAREST {
+            match bullets.get(i) {
+                Some(&(j, ref offset)) => {
+                    let bullet = &world.bullets[j];
+                    observation.extend(&[1.0, offset.x, offset.y, bullet.direction().cos(),
+                                         bullet.direction().sin(), flag(bullet.owner != 0)]);
+                }
+                None => observation.extend(&[0.0; BULLET_SIZE])
+            }
+        }
+
+        observation
+    }
+}
+
+/// Runs the environment for a process on the other side of a pipe
+///
+/// Each line of the input is a command, answered with a line of output:
+///
+/// * `reset <seed>` answers with the first observation.
+/// * `step <action>` answers with the reward, whether the game is over (1 or
+///   0) and the next observation.
+/// * `quit` stops without an answer, like the end of the input.
+///
+/// Numbers are separated by spaces. Invalid commands are answered with a line
+/// starting with `error`.
+pub fn run<R: BufRead, W: Write>(env: &mut Environment, input: R, mut output: W) -> Result<(), String> {
+    for line in input.lines() {
+        let line = try!(line.map_err(|e| format!("Unable to read a command: {}", e)));
+        let words: Vec<_> = line.split_whitespace().collect();
+        let answer = match (words.first().cloned(), words.len()) {
+            (Some("reset"), 2) => match words[1].parse() {
+                Ok(seed) => match env.reset(seed) {
+                    Ok(observation) => join(&observation),
+                    Err(e) => format!("error {}", e)
+                },
+                Err(_) => format!("error invalid seed `{}`", words[1])
+            },
+            (Some("step"), 2) => match words[1].parse() {
+                Ok(action) if action < ACTIONS => {
+                    let (observation, reward, done) = env.step(action);
+                    format!("{} {} {}", reward, flag(done), join(&observation))
+                }
+                _ => format!("error invalid action `{}`", words[1])
+            },
+            (Some("quit"), 1) => return Ok(()),
+            _ => format!("error unknown command `{}`", line)
+        };
+        try!(writeln!(output, "{}", answer).and_then(|_| output.flush())
+                 .map_err(|e| format!("Unable to write an answer: {}", e)));
+    }
+    Ok(())
+}
+
+/// Returns the shortest offset from one point to another as a fraction of the
+/// size of the world, which wraps around
+fn offset(from: &Point, to: &Point, size: &Size) -> Point {
+    let offset = size.offset(from, to);
+    Point::new(offset.x / size.width, offset.y / size.height)
+}
+
+/// Returns the `NEAREST` smallest offsets, closest first, with their index
+fn nearest<I: Iterator<Item=Point>>(offsets: I) -> Vec<(usize, Point)> {
+    let mut offsets: Vec<_> = offsets.enumerate().collect();
+    offsets.sort_by(|a, b| length(&a.1).partial_cmp(&length(&b.1)).unwrap());
+    offsets.truncate(NEAREST);
+    offsets
+}
+
+fn length(offset: &Point) -> f64 {
+    offset.x * offset.x + offset.y * offset.y
+}
+
+fn flag(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+/// Returns the numbers separated by spaces
+fn join(numbers: &[f64]) -> String {
+    numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use game::Mode;
+    use super::{Environment, OBSERVATION_SIZE};
+
+    /// Plays the same actions in two environments with the same seed and
+    /// checks that they see and earn the same
+    #[test]
+    fn deterministic() {
+        let mut a = Environment::new(Mode::Single).unwrap();
+        let mut b = Environment::new(Mode::Single).unwrap();
+        assert_eq!(a.reset(5).unwrap(), b.reset(5).unwrap());
+
+        let mut rewards = 0.0;
+        for i in 0..1200 {
+            let action = (i / 30 % 16) as u8;
+            let (observation, reward, done) = a.step(action);
+            assert_eq!(observation.len(), OBSERVATION_SIZE);
+            assert_eq!(b.step(action), (observation, reward, done));
+            rewards += reward;
+        }
+        assert!(rewards != 0.0);
+    }
+
+    /// Drives the environment through its text protocol
+    #[test]
+    fn protocol() {
+        let mut env = Environment::new(Mode::Single).unwrap();
+        let mut output = vec![];
+        super::run(&mut env, &b"reset 3\nstep 9\nstep 16\nquit\nstep 0\n"[..], &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].split(' ').count(), OBSERVATION_SIZE);
+        assert_eq!(lines[1].split(' ').count(), OBSERVATION_SIZE + 2);
+        assert!(lines[2].starts_with("error"));
+    }
+}
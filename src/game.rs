@@ -1,75 +1,468 @@
 //! This module contains the game logic
 
 use std::f64;
-use std::path::Path;
+use std::hash::{Hash, Hasher, SipHasher};
+use std::mem;
 
 use graphics::{self, Transformed};
-use itertools;
-use opengl_graphics::GlGraphics;
-use opengl_graphics::glyph_cache::GlyphCache;
 use piston::input::Key;
-use rand::{self, Rng, ThreadRng};
+use rand::Rng;
 
+use audio::Sound;
 use drawing::{color, Point, Size};
-use models::{Bullet, Enemy, Particle, Vector, World};
+use effects::Effects;
+use level::Level;
+use models::{Bullet, Enemy, Obstacle, ParticlePool, Pickup, Player, Telegraph, Vector, World};
+use models::{player, steering};
+use models::enemy::Kind;
+use renderer::Renderer;
+use rewind::History;
+use rng::GameRng;
+use spawn;
 use traits::{Advance, Collide, Position};
+use waves::Waves;
 
 const UPS: u16 = 120;
 const BULLET_RATE: f64 = 0.01;
 
+/// How many times larger than the screen the world is, in each dimension
+const WORLD_SCALE: f64 = 2.0;
+
+/// The space around the players the camera tries to keep visible, in pixels
+const CAMERA_MARGIN: f64 = 300.0;
+
+/// The camera doesn't zoom out further than this to keep the players visible
+const MIN_ZOOM: f64 = 0.5;
+
+/// The length of a versus round, in seconds
+const ROUND_LENGTH: f64 = 120.0;
+
+/// How long the winner of a versus round is shown before the next round starts, in seconds
+const WINNER_SCREEN: f64 = 5.0;
+
+/// The amount of random positions considered when looking for a safe point to respawn
+const SAFE_POINT_CANDIDATES: usize = 16;
+
+/// The room a safe point needs around it, free of obstacles, in pixels
+const SAFE_POINT_ROOM: f64 = 30.0;
+
+/// The points a player gets for collecting a pickup
+const PICKUP_SCORE: u32 = 50;
+
+/// How long a pickup takes to come back after being collected, in seconds
+const PICKUP_COOLDOWN: f64 = 15.0;
+
+/// The amount of updates between two moments of the rewind history
+const MOMENT_INTERVAL: u32 = 4;
+
+/// The amount of moments kept in the rewind history (10 seconds at 60 updates per second)
+const MOMENTS: usize = 150;
+
 /// The data structure that drives the game
+#[derive(Clone)]
 pub struct Game {
     /// The world contains everything that needs to be drawn
     world: World,
-    /// The current score of the player
-    score: u32,
-    /// The active actions
-    actions: Actions,
+    /// The game mode
+    mode: Mode,
+    /// The active actions of each player
+    actions: Vec<Actions>,
     /// Timers needed by the game
     timers: Timers,
     /// A random number generator
-    rng: ThreadRng,
+    rng: GameRng,
     /// Resources needed for drawing
-    resources: Resources
+    resources: Resources,
+    /// The sounds fired during the last update
+    sounds: Vec<Sound>,
+    /// The events of the last update
+    events: Vec<Event>,
+    /// The recent moments of the game, to rewind time
+    history: History<Moment>,
+    /// True while the rewind key is held
+    rewind_held: bool,
+    /// While rewinding, the last moment restored and the amount of updates
+    /// rewound since then
+    rewinding: Option<(Moment, u32)>,
+    /// How the play area adapts to the size of the window
+    scale_mode: ScaleMode,
+    /// The logical resolution of the play area
+    resolution: Size,
+    /// The current size of the window
+    screen: Size,
+    /// True if the world keeps its size when the window grows, because a
+    /// level decided it
+    fixed_size: bool
+}
+
+/// The game modes
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Mode {
+    /// A single player against the invaders
+    Single,
+    /// Two players sharing the keyboard, fighting the invaders together
+    Coop,
+    /// The given amount of players sharing the keyboard and shooting each
+    /// other in timed rounds, without invaders
+    Versus(usize)
+}
+
+impl Mode {
+    /// Returns the amount of players in the mode
+    pub fn players(&self) -> usize {
+        match *self {
+            Mode::Single => 1,
+            Mode::Coop => 2,
+            Mode::Versus(players) => players
+        }
+    }
+
+    /// Returns the name of the mode, as used in replays
+    pub fn name(&self) -> String {
+        match *self {
+            Mode::Single => "single".to_string(),
+            Mode::Coop => "coop".to_string(),
+            Mode::Versus(players) => format!("versus-{}", players)
+        }
+    }
+
+    /// Returns the mode with the given name
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "single" => Some(Mode::Single),
+            "coop" => Some(Mode::Coop),
+            _ if name.starts_with("versus-") => {
+                name["versus-".len()..].parse().ok().and_then(Mode::versus)
+            }
+            _ => None
+        }
+    }
+
+    /// Returns a versus mode for the given amount of players, if there are
+    /// enough keys and colors for all of them
+    pub fn versus(players: usize) -> Option<Mode> {
+        if 2 <= players && players <= CONTROLS.len() {
+            Some(Mode::Versus(players))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the players fight each other instead of the invaders
+    pub fn is_versus(&self) -> bool {
+        match *self {
+            Mode::Versus(_) => true,
+            _ => false
+        }
+    }
+
+    /// Returns the line of the HUD describing the player with the given index:
+    /// the score and lives, or the kills and deaths in versus mode
+    pub fn summary(&self, index: usize, player: &Player) -> String {
+        match *self {
+            Mode::Single => format!("Score: {}  Lives: {}", player.score, player.lives),
+            Mode::Coop => format!("P{} Score: {}  Lives: {}", index + 1, player.score, player.lives),
+            Mode::Versus(_) => format!("P{} Kills: {}  Deaths: {}", index + 1, player.kills, player.deaths)
+        }
+    }
+}
+
+/// How the play area adapts to the size of the window
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScaleMode {
+    /// The play area keeps its logical resolution and is scaled to fit
+    /// the window, with black bars filling the remaining space
+    Letterbox,
+    /// The play area grows with the window, showing more of the world. If
+    /// needed, the world grows too
+    Expand
+}
+
+/// Something that happened to a player during an update, for whoever keeps statistics
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// The given player fired a bullet
+    Shot(usize),
+    /// The given player shot down an enemy of the given kind or, in versus
+    /// mode, another player (without a kind)
+    Kill(usize, Option<Kind>),
+    /// The given player lost a life (or was shot down in versus mode)
+    Death(usize, Cause)
+}
+
+/// What caused the death of a player
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cause {
+    /// A collision with an enemy
+    Enemy,
+    /// A bullet fired by the given player
+    Player(usize),
+    /// The core of a black hole or a laser beam
+    Hazard
 }
 
 /// Active actions (toggled by user input)
-#[derive(Default)]
-struct Actions {
-    rotate_left: bool,
-    rotate_right: bool,
-    boost: bool,
-    shoot: bool
+#[derive(Clone, Default, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Actions {
+    pub rotate_left: bool,
+    pub rotate_right: bool,
+    pub boost: bool,
+    pub shoot: bool
+}
+
+impl Actions {
+    /// Updates the actions after a key press or release, according to the keys of the given player
+    pub fn handle_key(&mut self, player: usize, key: Key, pressed: bool) {
+        let controls = &CONTROLS[player];
+        if key == controls.rotate_left {
+            self.rotate_left = pressed;
+        } else if key == controls.rotate_right {
+            self.rotate_right = pressed;
+        } else if key == controls.boost {
+            self.boost = pressed;
+        } else if key == controls.shoot {
+            self.shoot = pressed;
+        }
+    }
+}
+
+/// The keys controlling a player
+struct Controls {
+    rotate_left: Key,
+    rotate_right: Key,
+    boost: Key,
+    shoot: Key
 }
 
+/// The keys of each player, in order
+const CONTROLS: [Controls; 4] = [
+    Controls { rotate_left: Key::Left, rotate_right: Key::Right, boost: Key::Up, shoot: Key::Space },
+    Controls { rotate_left: Key::A, rotate_right: Key::D, boost: Key::W, shoot: Key::LShift },
+    Controls { rotate_left: Key::J, rotate_right: Key::L, boost: Key::I, shoot: Key::K },
+    Controls { rotate_left: Key::NumPad4, rotate_right: Key::NumPad6, boost: Key::NumPad8, shoot: Key::NumPad5 }
+];
+
 /// Timers to handle creation of bullets, enemies and particles
-#[derive(Default)]
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
 struct Timers {
     current_time: f64,
     last_tail_particle: f64,
-    last_shoot: f64,
-    last_spawned_enemy: f64
+    /// The last time each player shot
+    last_shoot: Vec<f64>,
+    last_spawned_enemy: f64,
+    /// The time at which the current versus round ends
+    round_end: f64,
+    /// The time at which the next wave of enemies appears
+    next_wave: f64,
+    /// The amount of waves that have appeared
+    wave: usize
 }
 
 /// Additional resources needed for the game
+#[derive(Clone)]
 struct Resources {
-    font: GlyphCache<'static>
+    effects: Effects,
+    waves: Waves
+}
+
+/// A moment of the game, as kept in the rewind history
+///
+/// Unlike `State`, it leaves out the particles (which are simply moved back
+/// in time while rewinding) and the camera, so moments stay small
+#[derive(Clone)]
+struct Moment {
+    players: Vec<Player>,
+    enemies: Vec<Enemy>,
+    telegraphs: Vec<Telegraph>,
+    bullets: Vec<Bullet>,
+    pickups: Vec<Pickup>,
+    timers: Timers,
+    rng: GameRng
+}
+
+/// Everything needed to continue a game exactly where it was, as stored in save files
+///
+/// Resources and the size of the window don't belong to the game itself, so
+/// they are left out
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct State {
+    world: World,
+    mode: Mode,
+    actions: Vec<Actions>,
+    timers: Timers,
+    rng: GameRng
 }
 
 impl Game {
-    /// Returns a new `Game` containing a `World` of the given `Size`
-    pub fn new(size: Size) -> Game {
-        let mut rng = rand::thread_rng();
-        Game {
-            world: World::new(&mut rng, size),
-            score: 0,
-            actions: Actions::default(),
-            timers: Timers::default(),
+    /// Returns a new `Game` in the given mode, for a screen of the given `Size`
+    ///
+    /// The `World` is larger than the screen, and the camera follows the players around.
+    /// Two games created with the same seed and receiving the same actions will
+    /// play out exactly the same. Fails if the resources of the game can't be loaded
+    pub fn new(size: Size, scale_mode: ScaleMode, mode: Mode, seed: u64) -> Result<Game, String> {
+        let mut rng = GameRng::new(seed);
+        let world_size = Size::new(size.width * WORLD_SCALE, size.height * WORLD_SCALE);
+        let players = mode.players();
+        let waves = try!(Waves::load("resources/waves.toml"));
+        let effects = try!(Effects::load("resources/effects.toml"));
+        Ok(Game {
+            world: World::new(&mut rng, world_size, size.clone(), players),
+            mode: mode,
+            actions: vec![Actions::default(); players],
+            timers: Timers {
+                last_shoot: vec![0.0; players],
+                round_end: ROUND_LENGTH,
+                next_wave: waves.delay(0),
+                .. Timers::default()
+            },
             rng: rng,
-            resources: Resources { font: GlyphCache::new(&Path::new("resources/FiraMono-Bold.ttf")).unwrap() }
+            resources: Resources {
+                effects: effects,
+                waves: waves
+            },
+            sounds: vec![],
+            events: vec![],
+            history: History::new(MOMENTS, MOMENT_INTERVAL),
+            rewind_held: false,
+            rewinding: None,
+            scale_mode: scale_mode,
+            resolution: size.clone(),
+            screen: size,
+            fixed_size: false
+        })
+    }
+
+    /// Processes a change in the size of the window
+    pub fn resize(&mut self, width: f64, height: f64) {
+        self.screen = Size::new(width, height);
+
+        if self.scale_mode == ScaleMode::Expand {
+            // Show more of the world, and grow it if the screen is getting too
+            // large (unless its size is fixed)
+            self.world.camera.viewport = self.screen.clone();
+            if !self.fixed_size {
+                let size = &mut self.world.size;
+                size.width = size.width.max(width * WORLD_SCALE);
+                size.height = size.height.max(height * WORLD_SCALE);
+            }
+        }
+    }
+
+    /// Returns the state of the game, to save it
+    pub fn state(&self) -> State {
+        State {
+            world: self.world.clone(),
+            mode: self.mode,
+            actions: self.actions.clone(),
+            timers: self.timers.clone(),
+            rng: self.rng.clone()
+        }
+    }
+
+    /// Replaces the state of the game with a saved one
+    pub fn restore(&mut self, state: State) {
+        self.world = state.world;
+        self.mode = state.mode;
+        self.actions = state.actions;
+        self.timers = state.timers;
+        self.rng = state.rng;
+        self.history.clear();
+        self.rewinding = None;
+
+        // The game may have been saved with a window of a different size
+        self.world.camera.viewport = self.resolution.clone();
+        let (width, height) = (self.screen.width, self.screen.height);
+        self.resize(width, height);
+    }
+
+    /// Returns the scale and the offset of the play area inside the window
+    ///
+    /// In letterbox mode, the play area is scaled uniformly and centered
+    fn play_area(&self) -> (f64, f64, f64) {
+        match self.scale_mode {
+            ScaleMode::Letterbox => {
+                let scale = (self.screen.width / self.resolution.width)
+                            .min(self.screen.height / self.resolution.height);
+                (scale,
+                 (self.screen.width - self.resolution.width * scale) / 2.0,
+                 (self.screen.height - self.resolution.height * scale) / 2.0)
+            }
+            ScaleMode::Expand => (1.0, 0.0, 0.0)
         }
     }
 
+    /// Returns the game mode
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the world, with everything in it
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Returns the world to change its layout, like the level editor does
+    ///
+    /// The rewind history no longer matches the world afterwards, so it is dropped
+    pub fn edit(&mut self) -> &mut World {
+        self.history.clear();
+        self.rewinding = None;
+        &mut self.world
+    }
+
+    /// Lays out the world following the given level and starts the game again
+    pub fn set_level(&mut self, level: &Level) {
+        level.apply(&mut self.world);
+        self.history.clear();
+        self.rewinding = None;
+        self.reset();
+    }
+
+    /// Gives the world the given size, which it keeps regardless of the size of the window
+    pub fn set_size(&mut self, size: Size) {
+        self.world.size = size;
+        self.fixed_size = true;
+    }
+
+    /// Replaces the waves of enemies of the game, starting again from the first one
+    pub fn set_waves(&mut self, waves: Waves) {
+        self.resources.waves = waves;
+        self.timers.wave = 0;
+        self.timers.next_wave = self.timers.current_time + self.resources.waves.delay(0);
+    }
+
+    /// Returns a context whose transform maps world coordinates to the window
+    pub fn world_context(&self, c: graphics::context::Context) -> graphics::context::Context {
+        let (scale, x, y) = self.play_area();
+        self.world.camera.apply(c.trans(x, y).scale(scale, scale))
+    }
+
+    /// Returns the point of the world shown at the given point of the window
+    pub fn to_world(&self, point: &Point) -> Point {
+        let (scale, x, y) = self.play_area();
+        self.world.camera.to_world(&Point::new((point.x - x) / scale, (point.y - y) / scale))
+    }
+
+    /// Returns the active actions of each player
+    pub fn actions(&self) -> &[Actions] {
+        &self.actions
+    }
+
+    /// Returns the sounds fired during the last update
+    pub fn sounds(&self) -> &[Sound] {
+        &self.sounds
+    }
+
+    /// Returns the events of the last update
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Replaces the active actions of the given player, as if the corresponding keys were pressed
+    pub fn set_actions(&mut self, player: usize, actions: Actions) {
+        self.actions[player] = actions;
+    }
+
     /// Processes a key press
     pub fn key_press(&mut self, key: Key) {
         self.handle_key(key, true);
@@ -80,77 +473,272 @@ impl Game {
         self.handle_key(key, false);
     }
 
+    /// Picks up any changes to the effects file (only in debug builds)
+    ///
+    /// `dt` is the amount of seconds that have passed since the last call
+    pub fn reload_effects(&mut self, dt: f64) -> Result<(), String> {
+        self.resources.effects.update(dt)
+    }
+
+    /// Stops keeping a rewind history, which games that are cloned often
+    /// (like online ones) can't afford
+    pub fn disable_rewind(&mut self) {
+        self.history = History::new(0, MOMENT_INTERVAL);
+    }
+
     /// Handles a key press or release
     fn handle_key(&mut self, key: Key, pressed: bool) {
-        match key {
-            Key::Left => self.actions.rotate_left = pressed,
-            Key::Right => self.actions.rotate_right = pressed,
-            Key::Up => self.actions.boost = pressed,
-            Key::Space => self.actions.shoot = pressed,
-            _ => ()
+        if key == Key::Backspace && self.history.is_enabled() {
+            self.rewind_held = pressed;
+        }
+
+        for (i, actions) in self.actions.iter_mut().enumerate() {
+            actions.handle_key(i, key, pressed);
+        }
+    }
+
+    /// Returns a checksum of the state of the simulation
+    ///
+    /// Two games that played out the same have the same checksum, which is used
+    /// to detect when the games of an online match get out of sync. Particles and
+    /// the camera are only visual, so they are left out
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = SipHasher::new();
+        for &time in &[self.timers.current_time, self.timers.last_spawned_enemy, self.timers.round_end,
+                       self.timers.next_wave] {
+            hash_f64(&mut hasher, time);
+        }
+        hasher.write_usize(self.timers.wave);
+        self.rng.hash(&mut hasher);
+
+        for player in &self.world.players {
+            hash_vector(&mut hasher, &player.vector);
+            hash_f64(&mut hasher, player.invulnerable);
+            hash_f64(&mut hasher, player.drift.x);
+            hash_f64(&mut hasher, player.drift.y);
+            for &n in &[player.score, player.lives, player.kills, player.deaths] {
+                hasher.write_u32(n);
+            }
+        }
+        for enemy in &self.world.enemies {
+            hash_vector(&mut hasher, &enemy.vector);
+            hasher.write_u8(enemy.kind as u8);
+            hash_f64(&mut hasher, enemy.charge);
+            hasher.write_u32(enemy.health);
+        }
+        for telegraph in &self.world.telegraphs {
+            hash_vector(&mut hasher, &telegraph.enemy.vector);
+            hash_f64(&mut hasher, telegraph.time_left);
         }
+        for bullet in &self.world.bullets {
+            hash_vector(&mut hasher, &bullet.vector);
+            hasher.write_usize(bullet.owner);
+        }
+        for pickup in &self.world.pickups {
+            hash_f64(&mut hasher, pickup.cooldown);
+        }
+        hasher.finish()
     }
 
     /// Renders the game to the screen
-    pub fn render(&mut self, c: graphics::context::Context, g: &mut GlGraphics) {
+    pub fn render<R: Renderer>(&mut self, c: graphics::context::Context, r: &mut R) {
         // Clear everything
-        graphics::clear(color::BLACK, g);
+        r.clear(color::BLACK);
+
+        // Render the world inside the play area
+        let (scale, x, y) = self.play_area();
+        self.world.render(c.trans(x, y).scale(scale, scale), r);
+
+        // Cover anything drawn outside the play area with black bars
+        let (width, height) = (self.screen.width, self.screen.height);
+        let bars = [[0.0, 0.0, x, height], [width - x, 0.0, x, height],
+                    [0.0, 0.0, width, y], [0.0, height - y, width, y]];
+        for bar in bars.iter().filter(|bar| bar[2] > 0.0 && bar[3] > 0.0) {
+            r.rectangle(color::BLACK, *bar, c.transform);
+        }
+
+        // The HUD is drawn in screen coordinates, so it stays in the corners
+        // of the window regardless of the scale
+        self.world.render_minimap(c, r, &self.screen);
+
+        // Render the score and lives of each player (or their kills and deaths
+        // in versus mode): the first one in the top left corner and the rest
+        // in the bottom left corner
+        let players = self.world.players.len();
+        for (i, player) in self.world.players.iter().enumerate() {
+            let y = if i == 0 { 20.0 } else { self.screen.height - 12.0 - 24.0 * (players - 1 - i) as f64 };
+            let color = if self.mode == Mode::Single { color::ORANGE } else { player.color };
+            r.text(color, 22, &self.mode.summary(i, player), c.trans(10.0, y).transform);
+        }
+
+        if self.mode.is_versus() {
+            self.render_round(c, r);
+        }
+
+        // While rewinding, show how far back the history goes
+        if self.rewinding.is_some() {
+            let oldest = self.history.oldest().map_or(self.timers.current_time, |m| m.timers.current_time);
+            let seconds = self.timers.current_time - oldest;
+            let text = format!("<< Rewinding ({:.1}s left)", seconds);
+            render_centered(r, color::VIOLET, 22, &text, c.trans(self.screen.width / 2.0, self.screen.height - 40.0));
+        }
+    }
+
+    /// Renders the time left in the round at the top of the screen or, once
+    /// the round is over, the winner in the middle of it
+    fn render_round<R: Renderer>(&self, c: graphics::context::Context, r: &mut R) {
+        if !self.is_round_over() {
+            let left = (self.timers.round_end - self.timers.current_time).ceil() as u32;
+            let text = format!("{}:{:02}", left / 60, left % 60);
+            render_centered(r, color::ORANGE, 22, &text, c.trans(self.screen.width / 2.0, 20.0));
+            return;
+        }
+
+        let (text, color) = match self.winner() {
+            Some(i) => (format!("Player {} wins!", i + 1), self.world.players[i].color),
+            None => ("Draw!".to_string(), color::ORANGE)
+        };
+        let (x, y) = (self.screen.width / 2.0, self.screen.height / 2.0);
+        r.rectangle([0.0, 0.0, 0.0, 0.6], [0.0, y - 70.0, self.screen.width, 50.0 + 30.0 * self.world.players.len() as f64], c.transform);
+        render_centered(r, color, 40, &text, c.trans(x, y - 30.0));
+        for (i, player) in self.world.players.iter().enumerate() {
+            let line = format!("P{}  {} kills  {} deaths", i + 1, player.kills, player.deaths);
+            render_centered(r, player.color, 22, &line, c.trans(x, y + 10.0 + 30.0 * i as f64));
+        }
+    }
+
+    /// Returns true if the current versus round has ended and the winner is being shown
+    pub fn is_round_over(&self) -> bool {
+        self.timers.current_time >= self.timers.round_end
+    }
 
-        // Render the world
-        self.world.render(c, g);
+    /// Returns the index of the player winning the round, or `None` if there is a draw
+    ///
+    /// The winner is the player with the most kills. Ties are broken by the
+    /// amount of deaths
+    fn winner(&self) -> Option<usize> {
+        let players = &self.world.players;
+        let mut best = 0;
+        for i in 1..players.len() {
+            if (players[i].kills, players[best].deaths) > (players[best].kills, players[i].deaths) {
+                best = i;
+            }
+        }
 
-        // Render the score
-        let mut text = graphics::Text::new(22);
-        text.color = color::ORANGE;
-        text.draw(&format!("Score: {}", self.score),
-                  &mut self.resources.font,
-                  &c.draw_state,
-                  c.trans(10.0, 20.0).transform,
-                  g);
+        let tied = players.iter().filter(|p| p.kills == players[best].kills
+                                             && p.deaths == players[best].deaths).count();
+        if tied == 1 { Some(best) } else { None }
     }
 
     /// Updates the game
     ///
     /// `dt` is the amount of seconds that have passed since the last update
     pub fn update(&mut self, dt: f64) {
+        if self.rewind_held {
+            self.rewind(dt);
+            return;
+        }
+
+        // After rewinding, continue from the last moment restored. The objects
+        // shown between moments were only interpolated
+        if let Some((moment, _)) = self.rewinding.take() {
+            self.restore_moment(moment);
+        }
+
+        if self.history.tick() {
+            let moment = self.moment();
+            self.history.push(moment);
+        }
+
         self.timers.current_time += dt;
+        self.world.time = self.timers.current_time;
+        self.sounds.clear();
+        self.events.clear();
 
-        // Update rocket rotation
-        if self.actions.rotate_left {
-            *self.world.player.direction_mut() += (-0.06 * UPS as f64) * dt;
+        // Once a versus round is over, everything stands still while the
+        // winner is shown, and then a new round starts
+        if self.mode.is_versus() && self.is_round_over() {
+            self.world.particles.update(dt);
+            if self.timers.current_time >= self.timers.round_end + WINNER_SCREEN {
+                self.reset();
+            }
+            return;
         }
-        if self.actions.rotate_right {
-            *self.world.player.direction_mut() += (0.06 * UPS as f64) * dt;
-        };
 
-        // Set speed and advance the player with wrap around
-        let speed = if self.actions.boost { 470.0  } else { 200.0 };
-        self.world.player.advance_wrapping(dt * speed, self.world.size.clone());
+        // Update the rockets, which the gravity wells pull towards them
+        let forces: Vec<_> = self.world.players.iter().map(|p| self.world.gravity(&p.position())).collect();
+        for (i, player) in self.world.players.iter_mut().enumerate() {
+            if !player.is_alive() {
+                continue;
+            }
+
+            let actions = &self.actions[i];
+            if actions.rotate_left {
+                *player.direction_mut() += (-0.06 * UPS as f64) * dt;
+            }
+            if actions.rotate_right {
+                *player.direction_mut() += (0.06 * UPS as f64) * dt;
+            };
 
-        // Update particles
-        for particle in &mut self.world.particles {
-            particle.update(dt);
+            // Set speed and move the player, wrapping around unless the edges are walls
+            let speed = if actions.boost { 470.0  } else { 200.0 };
+            player.fly(dt, speed, &forces[i]);
+            if !self.world.walls {
+                player.wrap(self.world.size.clone());
+            }
+            confine(player, &self.world.obstacles, &self.world.size, self.world.walls);
+            player.invulnerable = (player.invulnerable - dt).max(0.0);
         }
 
-        // Remove old particles
-        self.world.particles.retain(|p| p.ttl > 0.0);
+        self.update_camera(dt);
+
+        // Update particles, recycling the ones whose time is up
+        self.world.particles.update(dt);
 
-        // Add new particles at the player's position, to leave a trail
+        // Add new particles at the players' positions, to leave a trail
         if self.timers.current_time - self.timers.last_tail_particle > 0.05 {
             self.timers.last_tail_particle = self.timers.current_time;
-            self.world.particles.push(Particle::new(self.world.player.vector.clone().invert(), 0.5));
+            for player in self.world.players.iter().filter(|p| p.is_alive()) {
+                self.resources.effects.emit("trail", &mut self.world.particles, player.vector.clone().invert());
+            }
         }
 
         // Add bullets
-        if self.actions.shoot && self.timers.current_time - self.timers.last_shoot > BULLET_RATE {
-            self.timers.last_shoot = self.timers.current_time;
-            let bullet_angle = if self.actions.boost {self.rng.gen::<f64>() - 0.5} else {0.};
-            self.world.bullets.push(Bullet::new(Vector::new(self.world.player.nose(), self.world.player.direction() + bullet_angle)));
+        for (i, player) in self.world.players.iter().enumerate() {
+            let actions = &self.actions[i];
+            if player.is_alive() && actions.shoot && self.timers.current_time - self.timers.last_shoot[i] > BULLET_RATE {
+                self.timers.last_shoot[i] = self.timers.current_time;
+                let bullet_angle = if actions.boost {self.rng.gen::<f64>() - 0.5} else {0.};
+                self.world.bullets.push(Bullet::new(Vector::new(player.nose(), player.direction() + bullet_angle), i));
+                self.sounds.push(Sound::Shoot);
+                self.events.push(Event::Shot(i));
+            }
+        }
+
+        // Advance bullets, bending their paths around the gravity wells
+        let forces: Vec<_> = self.world.bullets.iter().map(|b| self.world.gravity(&b.position())).collect();
+        for (bullet, force) in self.world.bullets.iter_mut().zip(forces) {
+            bullet.update(dt, &force);
         }
 
-        // Advance bullets
-        for bullet in &mut self.world.bullets {
-            bullet.update(dt * 500.0);
+        // Bullets that hit an obstacle are stopped by it, unless they bounce off
+        { // Shorten the lifetime of obstacles
+        let obstacles = &self.world.obstacles;
+        let particles = &mut self.world.particles;
+        let effects = &self.resources.effects;
+        let bullets = mem::replace(&mut self.world.bullets, vec![]);
+        self.world.bullets = bullets.into_iter().filter_map(|mut bullet| {
+            for obstacle in obstacles {
+                if let Some(push) = obstacle.push_out(&bullet.position(), bullet.radius()) {
+                    if !obstacle.ricochet {
+                        effects.emit("hit_sparks", particles, bullet.vector.clone().invert());
+                        return None;
+                    }
+                    bullet.ricochet(&push);
+                }
+            }
+            Some(bullet)
+        }).collect();
         }
 
         // Remove bullets outside the viewport
@@ -159,93 +747,450 @@ impl Game {
         self.world.bullets.retain(|b| size.contains(b.position()));
         }
 
-        // Spawn enemies at random locations
-        if self.timers.current_time - self.timers.last_spawned_enemy > 1.0 {
+        // Spawn enemies at safe locations (there are no enemies in versus mode). If
+        // there is no room left, the enemy is skipped until the next one is due
+        if !self.mode.is_versus() && self.timers.current_time - self.timers.last_spawned_enemy > 1.0 {
             self.timers.last_spawned_enemy = self.timers.current_time;
-            let mut new_enemy: Enemy;
-            loop {
-                new_enemy = Enemy::new(Vector::random(&mut self.rng, self.world.size.clone()));
-                if !self.world.player.collides_with(&new_enemy) {
-                    break;
-                }
+            let mut new_enemy = Enemy::new(Vector::default(), Kind::random(&mut self.rng));
+            if let Some(position) = spawn::find(&mut self.rng, new_enemy.radius(), &self.world, &self.resolution) {
+                new_enemy.vector = Vector::new(position, self.rng.gen());
+                self.world.enemies.push(new_enemy);
             }
-            self.world.enemies.push(new_enemy);
         }
 
-        // Move enemies in the player's direction
-        for enemy in &mut self.world.enemies {
-            enemy.update(dt * 100.0, self.world.player.position());
+        // Announce the next wave of enemies, showing where they will appear
+        { // Shorten the lifetime of waves
+        let waves = &self.resources.waves;
+        if !self.mode.is_versus() && self.timers.current_time >= self.timers.next_wave - waves.warning {
+            let enemies = waves.spawn(self.timers.wave, &mut self.rng, &self.world.players, &self.world.size);
+            self.world.telegraphs.extend(enemies.into_iter().map(|enemy| Telegraph::new(enemy, waves.warning)));
+            self.sounds.push(Sound::WaveStart);
+            self.timers.wave += 1;
+            self.timers.next_wave += waves.delay(self.timers.wave);
+        }
+        }
+
+        // Once their warning is over, the enemies of the wave appear
+        for telegraph in &mut self.world.telegraphs {
+            telegraph.time_left -= dt;
+        }
+        let (due, pending): (Vec<_>, Vec<_>) = mem::replace(&mut self.world.telegraphs, vec![])
+                                                             .into_iter().partition(|t| t.time_left <= 0.0);
+        self.world.telegraphs = pending;
+        self.world.enemies.extend(due.into_iter().map(|telegraph| telegraph.enemy));
+
+        // Steer the enemies towards the players. Every enemy decides where to
+        // go before any of them moves, so they all react to the same world
+        let steering: Vec<_> = (0..self.world.enemies.len())
+            .map(|i| steering::steer(i, &self.world.enemies, &self.world.players, &self.world.bullets,
+                                     &self.world.obstacles))
+            .collect();
+        for (enemy, steering) in self.world.enemies.iter_mut().zip(steering) {
+            enemy.update(dt, steering);
+            confine(enemy, &self.world.obstacles, &self.world.size, self.world.walls);
         }
 
+        self.update_pickups(dt);
+        self.handle_hazards();
         self.handle_player_collisions();
         self.handle_bullet_collisions();
+        if self.mode.is_versus() {
+            self.handle_versus_collisions();
+        }
+    }
+
+    /// Lets the players collect the pickups they fly over, which come back after a while
+    fn update_pickups(&mut self, dt: f64) {
+        for pickup in &mut self.world.pickups {
+            if !pickup.is_available() {
+                pickup.cooldown -= dt;
+                continue;
+            }
+
+            let collector = self.world.players.iter().position(|p| p.is_alive() && p.collides_with(&*pickup));
+            if let Some(i) = collector {
+                self.world.players[i].score += PICKUP_SCORE;
+                pickup.cooldown = PICKUP_COOLDOWN;
+                self.resources.effects.emit("pickup_sparkles", &mut self.world.particles, Vector::new(pickup.position(), 0.0));
+                self.sounds.push(Sound::Pickup);
+            }
+        }
+    }
+
+    /// Moves the game back in time by an update, as long as there is history left
+    ///
+    /// Every few updates, the previous moment of the history is restored. In
+    /// between, the players, enemies and bullets are moved smoothly towards
+    /// it, and the particles are updated backwards
+    fn rewind(&mut self, dt: f64) {
+        self.sounds.clear();
+        self.events.clear();
+        let (anchor, updates) = match self.rewinding.take() {
+            Some(rewinding) => rewinding,
+            None => (self.moment(), 0)
+        };
+
+        let target = match self.history.latest().cloned() {
+            Some(target) => target,
+            None => {
+                // Out of history, so time stands still
+                self.rewinding = Some((anchor, updates));
+                return;
+            }
+        };
+
+        self.world.particles.update(-dt);
+        self.update_camera(dt);
+
+        let updates = updates + 1;
+        if updates >= self.history.interval() {
+            self.history.pop();
+            self.restore_moment(target.clone());
+            self.rewinding = Some((target, 0));
+        } else {
+            let t = updates as f64 / self.history.interval() as f64;
+            self.show_between(&anchor, &target, t);
+            self.rewinding = Some((anchor, updates));
+        }
+    }
+
+    /// Returns the current moment of the game
+    fn moment(&self) -> Moment {
+        Moment {
+            players: self.world.players.clone(),
+            enemies: self.world.enemies.clone(),
+            telegraphs: self.world.telegraphs.clone(),
+            bullets: self.world.bullets.clone(),
+            pickups: self.world.pickups.clone(),
+            timers: self.timers.clone(),
+            rng: self.rng.clone()
+        }
+    }
+
+    /// Takes the game back to the given moment
+    fn restore_moment(&mut self, moment: Moment) {
+        self.world.players = moment.players;
+        self.world.enemies = moment.enemies;
+        self.world.telegraphs = moment.telegraphs;
+        self.world.bullets = moment.bullets;
+        self.world.pickups = moment.pickups;
+        self.timers = moment.timers;
+        self.rng = moment.rng;
+        self.world.time = self.timers.current_time;
+    }
+
+    /// Places the objects between where they were in two moments, where `t == 0`
+    /// is the first one and `t == 1` the second one
+    ///
+    /// Objects are matched by their position in the lists, so enemies and
+    /// bullets are only moved if their amount didn't change
+    fn show_between(&mut self, from: &Moment, to: &Moment, t: f64) {
+        let size = self.world.size.clone();
+        for (player, (a, b)) in self.world.players.iter_mut().zip(from.players.iter().zip(&to.players)) {
+            player.vector = a.vector.lerp(&b.vector, t, &size);
+        }
+        if from.enemies.len() == to.enemies.len() {
+            for (enemy, (a, b)) in self.world.enemies.iter_mut().zip(from.enemies.iter().zip(&to.enemies)) {
+                enemy.vector = a.vector.lerp(&b.vector, t, &size);
+            }
+        }
+        if from.bullets.len() == to.bullets.len() {
+            for (bullet, (a, b)) in self.world.bullets.iter_mut().zip(from.bullets.iter().zip(&to.bullets)) {
+                bullet.vector = a.vector.lerp(&b.vector, t, &size);
+            }
+        }
+    }
+
+    /// Makes the camera follow the living players
+    ///
+    /// The camera aims at the middle point between the players and zooms out
+    /// if needed to keep all of them on the screen. It also zooms out a bit
+    /// while someone is boosting
+    fn update_camera(&mut self, dt: f64) {
+        let positions: Vec<_> = self.world.players.iter().filter(|p| p.is_alive()).map(|p| p.position()).collect();
+        if positions.is_empty() {
+            return;
+        }
+
+        let bounds = positions.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+                                           |b, p| [b[0].min(p.x), b[1].min(p.y), b[2].max(p.x), b[3].max(p.y)]);
+        let center = Point::new((bounds[0] + bounds[2]) / 2.0, (bounds[1] + bounds[3]) / 2.0);
+
+        let viewport = self.world.camera.viewport.clone();
+        let fit = (viewport.width / (bounds[2] - bounds[0] + CAMERA_MARGIN))
+                  .min(viewport.height / (bounds[3] - bounds[1] + CAMERA_MARGIN))
+                  .max(MIN_ZOOM).min(1.0);
+        let boost = if self.actions.iter().any(|a| a.boost) { 0.85 } else { 1.0 };
+
+        self.world.camera.zoom_to(fit * boost);
+        self.world.camera.update(dt, center, &self.world.size, &mut self.rng);
     }
 
     /// Handles collisions between the bullets and the enemies
     ///
-    /// When an enemy is reached by a bullet, both the enemy and the bullet
-    /// will be removed. Additionally, the score will be increased by 10
+    /// When an enemy is reached by a bullet, the bullet is removed and the enemy
+    /// loses health. Enemies out of health are removed too, and the player that
+    /// fired the bullet gets the points of the enemy
     fn handle_bullet_collisions(&mut self) {
-        let old_enemy_count = self.world.enemies.len();
-
-        { // We introduce a scope to shorten the lifetime of the borrows below
         // The references are to avoid using self in the closure
         // (the borrow checker doesn't like that)
         let bullets = &mut self.world.bullets;
         let enemies = &mut self.world.enemies;
+        let players = &mut self.world.players;
         let particles = &mut self.world.particles;
+        let camera = &mut self.world.camera;
+        let effects = &self.resources.effects;
+        let sounds = &mut self.sounds;
+        let events = &mut self.events;
 
         bullets.retain(|bullet| {
             // Remove the first enemy that collides with a bullet (if any)
-            // Add an explosion on its place
+            // Add an explosion on its place and sparks flying back from the impact
             if let Some((index, position)) = enemies.iter().enumerate()
                 .find(|&(_, enemy)| enemy.collides_with(bullet))
                 .map(|(index, enemy)| (index, enemy.position()))
             {
-                Game::make_explosion(particles, position, 10);
-                enemies.remove(index);
+                effects.emit("hit_sparks", particles, bullet.vector.clone().invert());
+                enemies[index].health -= 1;
+                if enemies[index].health == 0 {
+                    Game::make_explosion(effects, particles, sounds, position, "enemy_explosion", Sound::EnemyExplosion);
+                    camera.add_trauma(0.3);
+                    let kind = enemies.remove(index).kind;
+                    players[bullet.owner].score += kind.points();
+                    events.push(Event::Kill(bullet.owner, Some(kind)));
+                }
                 false
             } else {
                 true
             }
         });
-        }
-
-        let killed_enemies = (old_enemy_count - self.world.enemies.len()) as u32;
-        self.score += 10 * killed_enemies;
     }
 
     /// reset our game-state
     fn reset(&mut self) {
-        // Reset player position
-        *self.world.player.x_mut() = self.world.size.random_x(&mut self.rng);
-        *self.world.player.y_mut() = self.world.size.random_y(&mut self.rng);
-
-        // Reset score
-        self.score = 0;
-
         // Remove all enemies and bullets
         self.world.bullets.clear();
         self.world.enemies.clear();
+
+        // Reset the players' positions, scores and lives
+        for i in 0..self.world.players.len() {
+            let vector = self.safe_point(i);
+            let player = &mut self.world.players[i];
+            player.respawn(vector);
+            player.score = 0;
+            player.lives = player::LIVES;
+            player.kills = 0;
+            player.deaths = 0;
+        }
+
+        // Start a new round
+        self.timers.round_end = self.timers.current_time + ROUND_LENGTH;
+
+        // Make every pickup available again
+        for pickup in &mut self.world.pickups {
+            pickup.cooldown = 0.0;
+        }
+
+        // Start the waves again from the first one
+        self.world.telegraphs.clear();
+        self.timers.wave = 0;
+        self.timers.next_wave = self.timers.current_time + self.resources.waves.delay(0);
     }
 
-    /// Handles collisions between the player and the enemies
+    /// Returns a random position for the given player to respawn, as far
+    /// as possible from the other players and the enemies
+    ///
+    /// If the level has spawn points, the player respawns at one of them
+    fn safe_point(&mut self, player: usize) -> Vector {
+        if !self.world.spawn_points.is_empty() {
+            let mut best = 0;
+            let mut best_distance = -1.0;
+            for (i, point) in self.world.spawn_points.iter().enumerate() {
+                let distance = self.squared_clearance(player, point);
+                if distance > best_distance {
+                    best = i;
+                    best_distance = distance;
+                }
+            }
+            return Vector::new(self.world.spawn_points[best].clone(), self.rng.gen());
+        }
+
+        let mut best = Vector::random(&mut self.rng, self.world.size.clone());
+        let mut best_distance = 0.0;
+        for _ in 0..SAFE_POINT_CANDIDATES {
+            let candidate = Vector::random(&mut self.rng, self.world.size.clone());
+            if self.world.obstacles.iter().any(|o| o.push_out(&candidate.position, SAFE_POINT_ROOM).is_some())
+               || self.world.is_deadly(&candidate.position, SAFE_POINT_ROOM) {
+                continue;
+            }
+            let distance = self.squared_clearance(player, &candidate.position);
+            if distance > best_distance {
+                best = candidate;
+                best_distance = distance;
+            }
+        }
+        best
+    }
+
+    /// Returns the squared distance from the point to the closest enemy or
+    /// living player other than the given one
+    fn squared_clearance(&self, player: usize, point: &Point) -> f64 {
+        let others = self.world.players.iter().enumerate()
+                         .filter(|&(i, p)| i != player && p.is_alive())
+                         .map(|(_, p)| p.position());
+        let enemies = self.world.enemies.iter().map(|e| e.position());
+        others.chain(enemies)
+              .map(|p| p.squared_distance_to(point))
+              .fold(f64::INFINITY, |a, b| a.min(b))
+    }
+
+    /// Destroys the bullets, enemies and players caught by a hazard: the core
+    /// of a black hole or a laser beam. Bosses are too large to be destroyed,
+    /// so they must be shot down
+    fn handle_hazards(&mut self) {
+        if self.world.wells.is_empty() && self.world.lasers.is_empty() {
+            return;
+        }
+
+        let bullets = mem::replace(&mut self.world.bullets, vec![]);
+        let (lost, bullets): (Vec<_>, Vec<_>) = bullets.into_iter()
+            .partition(|b| self.world.is_deadly(&b.position(), b.radius()));
+        self.world.bullets = bullets;
+        for bullet in lost {
+            self.resources.effects.emit("hit_sparks", &mut self.world.particles, bullet.vector.invert());
+        }
+
+        let enemies = mem::replace(&mut self.world.enemies, vec![]);
+        let (lost, enemies): (Vec<_>, Vec<_>) = enemies.into_iter()
+            .partition(|e| e.kind != Kind::Boss && self.world.is_deadly(&e.position(), e.radius()));
+        self.world.enemies = enemies;
+        for enemy in lost {
+            Game::make_explosion(&self.resources.effects, &mut self.world.particles, &mut self.sounds,
+                                 enemy.position(), "enemy_explosion", Sound::EnemyExplosion);
+        }
+
+        for i in 0..self.world.players.len() {
+            let caught = {
+                let player = &self.world.players[i];
+                player.is_vulnerable() && self.world.is_deadly(&player.position(), player.radius())
+            };
+            if caught {
+                self.kill_player(i, Cause::Hazard);
+            }
+        }
+    }
+
+    /// Handles collisions between the players and the enemies
+    ///
+    /// A player that collides with an enemy loses a life and respawns somewhere
+    /// else. When every player is out of lives, the game starts again
     fn handle_player_collisions(&mut self) {
-        if self.world.enemies.iter().any(|enemy| self.world.player.collides_with(enemy)) {
-            // Make an explosion where the player was
-            let ppos = self.world.player.position();
-            Game::make_explosion(&mut self.world.particles, ppos, 8);
-            
+        for i in 0..self.world.players.len() {
+            if self.world.players[i].is_vulnerable()
+               && self.world.enemies.iter().any(|enemy| self.world.players[i].collides_with(enemy)) {
+                self.kill_player(i, Cause::Enemy);
+            }
+        }
+
+        if !self.world.players.iter().any(|p| p.is_alive()) {
             self.reset();
         }
     }
 
-    // Generates a new explosion of the given intensity at the given position. This works best with values between 5 and 25
-    fn make_explosion(particles: &mut Vec<Particle>, position: Point, intensity: u8) {
-        for rotation in itertools::linspace(0.0, 2.0 * f64::consts::PI, 30) {
-            for ttl in (1..intensity).map(|x| (x as f64) / 10.0) {
-                particles.push(Particle::new(Vector::new(position.clone(), rotation), ttl));
+    /// Handles collisions between the bullets and the players in versus mode
+    ///
+    /// A player reached by a bullet fired by someone else is shot down, and
+    /// the owner of the bullet gets the kill
+    fn handle_versus_collisions(&mut self) {
+        let mut hits = vec![];
+        {
+        let players = &self.world.players;
+        self.world.bullets.retain(|bullet| {
+            let victim = players.iter().enumerate().position(|(i, player)| {
+                i != bullet.owner && player.is_vulnerable() && player.collides_with(bullet)
+                && !hits.iter().any(|&(_, victim)| victim == i)
+            });
+            match victim {
+                Some(victim) => {
+                    hits.push((bullet.owner, victim));
+                    false
+                }
+                None => true
             }
+        });
+        }
+
+        for (killer, victim) in hits {
+            self.world.players[killer].kills += 1;
+            self.events.push(Event::Kill(killer, None));
+            self.kill_player(victim, Cause::Player(killer));
+        }
+    }
+
+    /// Makes the given player explode and respawn at a safe point
+    ///
+    /// In versus mode players have infinite lives, otherwise the player loses
+    /// one and only respawns if there are any left
+    fn kill_player(&mut self, i: usize, cause: Cause) {
+        // Make an explosion where the player was
+        let ppos = self.world.players[i].position();
+        Game::make_explosion(&self.resources.effects, &mut self.world.particles, &mut self.sounds,
+                             ppos, "player_explosion", Sound::PlayerDeath);
+        self.world.camera.add_trauma(0.8);
+
+        self.world.players[i].deaths += 1;
+        self.events.push(Event::Death(i, cause));
+        if !self.mode.is_versus() {
+            self.world.players[i].lives -= 1;
+        }
+        if self.world.players[i].is_alive() {
+            let vector = self.safe_point(i);
+            self.world.players[i].respawn(vector);
+        }
+    }
+
+    // Generates a new explosion at the given position, using the effect with the given name,
+    // and fires the given sound
+    fn make_explosion(effects: &Effects, particles: &mut ParticlePool, sounds: &mut Vec<Sound>,
+                      position: Point, effect: &str, sound: Sound) {
+        effects.emit(effect, particles, Vector::new(position, 0.0));
+        sounds.push(sound);
+    }
+}
+
+/// Renders a line of text horizontally centered on the origin of the context
+pub fn render_centered<R: Renderer>(r: &mut R, color: color::Color, size: u32, text: &str, c: graphics::context::Context) {
+    // The font is monospaced, and each character is about 0.6 times as wide as it is tall
+    let width = text.chars().count() as f64 * size as f64 * 0.6;
+    r.text(color, size, text, c.trans(-width / 2.0, 0.0).transform);
+}
+
+/// Moves the object out of the obstacles it overlaps and, if the edges of the
+/// world are walls, back inside the world
+fn confine<T: Collide>(object: &mut T, obstacles: &[Obstacle], size: &Size, walls: bool) {
+    for obstacle in obstacles {
+        if let Some(push) = obstacle.push_out(&object.position(), object.radius()) {
+            *object.x_mut() += push.x;
+            *object.y_mut() += push.y;
         }
     }
+
+    if walls {
+        let radius = object.radius();
+        let (x, y) = (object.x().max(radius).min(size.width - radius), object.y().max(radius).min(size.height - radius));
+        *object.x_mut() = x;
+        *object.y_mut() = y;
+    }
+}
+
+/// Feeds the exact bits of a float to the hasher
+fn hash_f64<H: Hasher>(hasher: &mut H, x: f64) {
+    hasher.write_u64(unsafe { mem::transmute(x) });
+}
+
+/// Feeds the position and the direction of a vector to the hasher
+fn hash_vector<H: Hasher>(hasher: &mut H, vector: &Vector) {
+    hash_f64(hasher, vector.position.x);
+    hash_f64(hasher, vector.position.y);
+    hash_f64(hasher, vector.direction);
 }
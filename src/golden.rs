@@ -0,0 +1,213 @@
+//! Visual regression tests
+//!
+//! Each scenario runs a seeded game with scripted input, renders some of its
+//! frames with the software renderer and compares them against the golden
+//! images in `tests/golden`. Small differences (like antialiasing) are
+//! tolerated. When a frame doesn't match, the actual frame and an image
+//! highlighting the differences are written to `target/golden`.
+//!
+//! A missing golden image fails the test. To record the golden images of a
+//! new scenario, or to overwrite them after an intended visual change, run
+//! the tests with `ROCKET_BLESS=1`, then review and commit the new images.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use graphics::Context;
+use image;
+
+use drawing::Size;
+use game::{Actions, Game, Mode, ScaleMode};
+use renderer::Canvas;
+use replay::Replay;
+
+/// The size of the rendered frames
+const WIDTH: u32 = 480;
+const HEIGHT: u32 = 300;
+
+/// The maximum difference in a color channel for two pixels to be considered equal
+const CHANNEL_TOLERANCE: u8 = 16;
+
+/// The maximum fraction of pixels that may differ for two frames to be considered equal
+const PIXEL_TOLERANCE: f64 = 0.001;
+
+/// A seeded game with scripted input
+struct Scenario {
+    name: &'static str,
+    replay: Replay,
+    /// The frames to compare, as the amount of updates before rendering them
+    frames: Vec<usize>
+}
+
+impl Scenario {
+    /// Returns a scenario without input for a game with the given mode and seed
+    fn new(name: &'static str, mode: Mode, seed: u64) -> Scenario {
+        Scenario { name: name, replay: Replay::new(seed, 60, mode), frames: vec![] }
+    }
+
+    /// Keeps the given actions of each player active during the given amount of updates
+    fn hold(mut self, actions: &[Actions], updates: usize) -> Scenario {
+        for _ in 0..updates {
+            self.replay.record(actions);
+        }
+        self
+    }
+
+    /// Adds a frame to compare, after the given amount of updates
+    fn frame(mut self, updates: usize) -> Scenario {
+        self.frames.push(updates);
+        self
+    }
+
+    /// Runs the scenario, comparing every frame against its golden image
+    fn check(&self) {
+        let bless = env::var("ROCKET_BLESS").is_ok();
+        let mut failures = vec![];
+
+        for &frame in &self.frames {
+            let canvas = self.render(frame);
+            let name = format!("{}-{}", self.name, frame);
+            let golden = Path::new("tests/golden").join(format!("{}.png", name));
+
+            if bless {
+                fs::create_dir_all("tests/golden").unwrap();
+                canvas.save_png(&golden).unwrap();
+                continue;
+            }
+
+            if fs::metadata(&golden).is_err() {
+                failures.push(format!("{}: missing golden image {}, run the tests with ROCKET_BLESS=1 to record it",
+                                      name, golden.display()));
+                continue;
+            }
+
+            if let Err(e) = compare(&canvas, &golden, &name) {
+                failures.push(e);
+            }
+        }
+
+        if !failures.is_empty() {
+            panic!("{}", failures.join("\n"));
+        }
+    }
+
+    /// Plays the scenario up to the given frame and renders it
+    fn render(&self, frame: usize) -> Canvas {
+        let mut game = Game::new(Size::new(WIDTH as f64, HEIGHT as f64), ScaleMode::Letterbox,
+                                 self.replay.mode, self.replay.seed).unwrap();
+        self.replay.play(&mut game, frame);
+
+        let mut canvas = Canvas::with_font(WIDTH, HEIGHT, "resources/FiraMono-Bold.ttf").unwrap();
+        game.render(Context::abs(WIDTH as f64, HEIGHT as f64), &mut canvas);
+        canvas
+    }
+}
+
+/// Compares the canvas against a golden image
+///
+/// If they differ, the canvas and an image highlighting the differing pixels
+/// in red are written to `target/golden`
+fn compare(canvas: &Canvas, golden: &Path, name: &str) -> Result<(), String> {
+    let expected = try!(image::open(golden).map_err(|e| format!("{}: {:?}", golden.display(), e))).to_rgba();
+    if expected.width() != canvas.width() || expected.height() != canvas.height() {
+        return Err(format!("{}: expected a {}x{} image, got {}x{}", name,
+                           expected.width(), expected.height(), canvas.width(), canvas.height()));
+    }
+
+    let expected = expected.into_raw();
+    let actual = canvas.pixels();
+
+    // Build the diff image while counting the differing pixels. Matching
+    // pixels are dimmed, so the differences stand out
+    let mut diff = Vec::with_capacity(actual.len());
+    let mut differing = 0;
+    for (a, e) in actual.chunks(4).zip(expected.chunks(4)) {
+        let equal = a.iter().zip(e.iter()).all(|(&a, &e)| (a as i16 - e as i16).abs() <= CHANNEL_TOLERANCE as i16);
+        if equal {
+            diff.extend([e[0] / 4, e[1] / 4, e[2] / 4, 255].iter().cloned());
+        } else {
+            differing += 1;
+            diff.extend([255, 0, 0, 255].iter().cloned());
+        }
+    }
+
+    let fraction = differing as f64 / (canvas.width() * canvas.height()) as f64;
+    if fraction <= PIXEL_TOLERANCE {
+        return Ok(());
+    }
+
+    let output = PathBuf::from("target/golden");
+    fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{}.png", name));
+    let diff_path = output.join(format!("{}-diff.png", name));
+    try!(canvas.save_png(&actual_path));
+    try!(image::save_buffer(&diff_path, &diff, canvas.width(), canvas.height(), image::ColorType::RGBA(8))
+             .map_err(|e| format!("Unable to write {}: {}", diff_path.display(), e)));
+
+    Err(format!("{}: {} pixels ({:.2}%) differ from the golden image, see {} and {}",
+                name, differing, fraction * 100.0, actual_path.display(), diff_path.display()))
+}
+
+/// Returns the given actions, where `keys` contains the first letter of each active one
+fn actions(keys: &str) -> Actions {
+    Actions {
+        rotate_left: keys.contains('L'),
+        rotate_right: keys.contains('R'),
+        boost: keys.contains('B'),
+        shoot: keys.contains('S')
+    }
+}
+
+#[test]
+fn idle() {
+    Scenario::new("idle", Mode::Single, 1)
+        .frame(0)
+        .frame(90)
+        .check();
+}
+
+#[test]
+fn shooting() {
+    Scenario::new("shooting", Mode::Single, 2)
+        .hold(&[actions("S")], 60)
+        .hold(&[actions("LS")], 60)
+        .frame(30)
+        .frame(120)
+        .check();
+}
+
+#[test]
+fn boosting() {
+    Scenario::new("boosting", Mode::Single, 3)
+        .hold(&[actions("B")], 45)
+        .hold(&[actions("RB")], 45)
+        .frame(90)
+        .check();
+}
+
+#[test]
+fn explosions() {
+    // Keep shooting in circles long enough for enemies to spawn and be hit
+    Scenario::new("explosions", Mode::Single, 4)
+        .hold(&[actions("LS")], 300)
+        .frame(300)
+        .check();
+}
+
+#[test]
+fn coop() {
+    Scenario::new("coop", Mode::Coop, 5)
+        .hold(&[actions("S"), actions("BS")], 60)
+        .hold(&[actions("LS"), actions("RS")], 60)
+        .frame(120)
+        .check();
+}
+
+#[test]
+fn versus() {
+    Scenario::new("versus", Mode::Versus(3), 6)
+        .hold(&[actions("LS"), actions("RS"), actions("BS")], 180)
+        .frame(180)
+        .check();
+}
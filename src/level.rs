@@ -0,0 +1,157 @@
+//! Levels, which lay out the world with obstacles, spawn points, pickups and hazards
+//!
+//! Levels are made with the level editor and stored as TOML files, with a
+//! list for each kind of object:
+//!
+//! ```text
+//! walls = true
+//!
+//! [[obstacles]]
+//! shape = 60.0
+//! ricochet = false
+//!
+//! [obstacles.position]
+//! x = 500.0
+//! y = 300.0
+//!
+//! [[spawn_points]]
+//! x = 100.0
+//! y = 100.0
+//! ```
+//!
+//! The shape of an obstacle is a number for a circle of that radius, a table
+//! with a `width` and a `height` for a rectangle, or a list of the corners of
+//! a convex polygon, relative to its position. When `walls` is true, the edges
+//! of the world are solid instead of wrapping around. A level without spawn
+//! points lets the players appear anywhere.
+//!
+//! The hazards are added to the file by hand. A well (`[[wells]]`) has a
+//! `position`, a `strength`, which is how hard it pulls at 100 pixels away, and
+//! a `core`, the radius of the black hole at its center (zero for none). A
+//! laser (`[[lasers]]`) is a beam between the points `from` and `to`, which
+//! sweeps by the offset `travel` and back every `period` seconds.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use rustc_serialize::Decodable;
+use toml;
+
+use drawing::Point;
+use models::{Laser, Obstacle, Pickup, Well, World};
+use traits::Position;
+
+/// The layout of a level
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
+pub struct Level {
+    pub obstacles: Vec<Obstacle>,
+    /// The places where the players appear
+    pub spawn_points: Vec<Point>,
+    /// The places where pickups lie
+    pub pickups: Vec<Point>,
+    /// True if the edges of the world are solid walls
+    pub walls: bool,
+    pub wells: Vec<Well>,
+    pub lasers: Vec<Laser>
+}
+
+impl Level {
+    /// Returns the layout of the given world
+    pub fn from_world(world: &World) -> Level {
+        Level {
+            obstacles: world.obstacles.clone(),
+            spawn_points: world.spawn_points.clone(),
+            pickups: world.pickups.iter().map(|p| p.position()).collect(),
+            walls: world.walls,
+            wells: world.wells.clone(),
+            lasers: world.lasers.clone()
+        }
+    }
+
+    /// Replaces the layout of the world with this one
+    pub fn apply(&self, world: &mut World) {
+        world.obstacles = self.obstacles.clone();
+        world.spawn_points = self.spawn_points.clone();
+        world.pickups = self.pickups.iter().cloned().map(Pickup::new).collect();
+        world.walls = self.walls;
+        world.wells = self.wells.clone();
+        world.lasers = self.lasers.clone();
+    }
+
+    /// Loads the level stored in the given file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, String> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+                 .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+        parse(&source).map_err(|e| format!("Invalid level {}: {}", path.display(), e))
+    }
+
+    /// Returns the contents of a level file describing this level
+    pub fn to_toml(&self) -> String {
+        toml::encode_str(self)
+    }
+
+    /// Saves the level to the given file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        File::create(path).and_then(|mut f| f.write_all(self.to_toml().as_bytes()))
+                          .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+}
+
+/// Returns the level described by the contents of a level file
+pub fn parse(source: &str) -> Result<Level, String> {
+    let mut parser = toml::Parser::new(source);
+    match parser.parse() {
+        Some(table) => Level::decode(&mut toml::Decoder::new(toml::Value::Table(table))).map_err(|e| e.to_string()),
+        None => {
+            let errors: Vec<_> = parser.errors.iter().map(|e| e.desc.clone()).collect();
+            Err(errors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::{Point, Size};
+    use models::{Laser, Obstacle, Shape, Well};
+    use super::{parse, Level};
+    use toml;
+
+    /// A level written to a file reads back the same, with every shape of obstacle and every hazard
+    #[test]
+    fn round_trip() {
+        let level = Level {
+            obstacles: vec![Obstacle::new(Point::new(500.0, 300.0), Shape::Circle(60.0), false),
+                            Obstacle::new(Point::new(200.0, 300.0), Shape::Rectangle(Size::new(40.0, 200.0)), true),
+                            Obstacle::new(Point::new(800.0, 300.0),
+                                          Shape::Polygon(vec![Point::new(0.0, -30.0), Point::new(30.0, 30.0),
+                                                              Point::new(-30.0, 30.0)]),
+                                          false)],
+            spawn_points: vec![Point::new(100.0, 100.0), Point::new(900.0, 500.0)],
+            pickups: vec![],
+            walls: true,
+            wells: vec![Well::new(Point::new(300.0, 500.0), 250.0, 20.0)],
+            lasers: vec![Laser::new(Point::new(0.0, 100.0), Point::new(200.0, 100.0), Point::new(0.0, 300.0), 4.0)]
+        };
+        let parsed = parse(&toml::encode_str(&level)).unwrap();
+
+        assert_eq!(parsed.obstacles.len(), 3);
+        match (&parsed.obstacles[0].shape, &parsed.obstacles[1].shape, &parsed.obstacles[2].shape) {
+            (&Shape::Circle(radius), &Shape::Rectangle(ref size), &Shape::Polygon(ref corners)) => {
+                assert_eq!(radius, 60.0);
+                assert_eq!(size.height, 200.0);
+                assert_eq!(corners.len(), 3);
+            }
+            _ => panic!("the shapes changed")
+        }
+        assert!(parsed.obstacles[1].ricochet);
+        assert_eq!(parsed.spawn_points[1].x, 900.0);
+        assert!(parsed.pickups.is_empty());
+        assert!(parsed.walls);
+        assert_eq!(parsed.wells[0].core, 20.0);
+        assert_eq!(parsed.lasers[0].travel.y, 300.0);
+    }
+}
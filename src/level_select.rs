@@ -0,0 +1,79 @@
+//! The level select screen of the campaign
+//!
+//! It lists the levels of the campaign with their objective and the stars
+//! earned in them. The levels after the first one that hasn't been
+//! completed are locked.
+
+use std::cmp;
+
+use graphics::{Context, Transformed};
+use piston::input::Key;
+
+use campaign::{stars_text, Campaign, Progress};
+use drawing::{color, Size};
+use game::render_centered;
+use renderer::Renderer;
+
+/// The space between two levels of the list, in pixels
+const LINE_HEIGHT: f64 = 50.0;
+
+/// The state of the level select screen
+pub struct LevelSelect {
+    /// The index of the highlighted level
+    selected: usize
+}
+
+/// What the level select screen asks for after a key press
+pub enum Choice {
+    /// Play the level with the given index
+    Play(usize),
+    /// Go back to the title screen
+    Back
+}
+
+impl LevelSelect {
+    /// Opens the level select screen, with the furthest unlocked level highlighted
+    pub fn new(campaign: &Campaign, progress: &Progress) -> LevelSelect {
+        let unlocked = (0..campaign.stages.len()).filter(|&i| progress.is_unlocked(i)).count();
+        LevelSelect { selected: cmp::max(unlocked, 1) - 1 }
+    }
+
+    /// Processes a key press: the arrows move between the unlocked levels,
+    /// Enter plays the highlighted one and Backspace goes back
+    pub fn key_press(&mut self, key: Key, campaign: &Campaign, progress: &Progress) -> Option<Choice> {
+        match key {
+            Key::Up if self.selected > 0 => self.selected -= 1,
+            Key::Down if self.selected + 1 < campaign.stages.len() && progress.is_unlocked(self.selected + 1) => {
+                self.selected += 1;
+            }
+            Key::Return if self.selected < campaign.stages.len() => return Some(Choice::Play(self.selected)),
+            Key::Backspace => return Some(Choice::Back),
+            _ => {}
+        }
+        None
+    }
+
+    /// Draws the list of levels over the demo
+    pub fn render<R: Renderer>(&self, c: Context, r: &mut R, campaign: &Campaign, progress: &Progress, screen: &Size) {
+        r.rectangle([0.0, 0.0, 0.0, 0.7], [0.0, 0.0, screen.width, screen.height], c.transform);
+
+        let x = screen.width / 2.0;
+        let top = (screen.height - LINE_HEIGHT * campaign.stages.len() as f64) / 2.0;
+        render_centered(r, color::ORANGE, 40, "CAMPAIGN", c.trans(x, top - 40.0));
+
+        for (i, stage) in campaign.stages.iter().enumerate() {
+            let y = top + 30.0 + LINE_HEIGHT * i as f64;
+            let (line, detail) = if progress.is_unlocked(i) {
+                (format!("{}. {}  {}", i + 1, stage.name, stars_text(progress.stars(i))), stage.objective.describe())
+            } else {
+                (format!("{}. Locked", i + 1), "Complete the previous level first".to_string())
+            };
+            let color = if i == self.selected { color::ORANGE } else { color::GREY };
+            render_centered(r, color, 22, &line, c.trans(x, y));
+            render_centered(r, color, 14, &detail, c.trans(x, y + 18.0));
+        }
+
+        let y = top + 30.0 + LINE_HEIGHT * campaign.stages.len() as f64;
+        render_centered(r, color::ORANGE, 16, "Up/Down choose  Enter play  Backspace back", c.trans(x, y));
+    }
+}
@@ -1,60 +1,641 @@
+#[cfg(feature = "audio")]
+extern crate ears;
+extern crate freetype;
 extern crate glutin_window;
 extern crate graphics;
-extern crate itertools;
+extern crate image;
 extern crate opengl_graphics;
 extern crate piston;
 extern crate rand;
+extern crate rustc_serialize;
+extern crate toml;
 
+mod audio;
+mod autopilot;
+mod batch;
+mod camera;
+mod campaign;
 mod drawing;
+mod editor;
+mod effects;
+mod environment;
 mod game;
+#[cfg(test)]
+mod golden;
+mod level;
+mod level_select;
 mod models;
+mod net;
+mod options;
+mod renderer;
+mod replay;
+mod rewind;
+mod rng;
+mod save;
+mod spawn;
 mod traits;
+mod waves;
+
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
 use glutin_window::GlutinWindow;
+use graphics::{Context, Transformed};
 use opengl_graphics::{GlGraphics, OpenGL};
+use opengl_graphics::glyph_cache::GlyphCache;
 use piston::event::{Event, Events, EventLoop, RenderEvent};
-use piston::input::{Button, Input};
-use piston::window::WindowSettings;
+use piston::input::{Button, Input, Key, Motion};
+use piston::window::{Window, WindowSettings};
+
+use audio::{synth, Audio};
+use campaign::{Attempt, Campaign, Outcome, Progress, Stage};
+use drawing::{color, Size};
+use editor::Editor;
+use environment::Environment;
+use game::{Game, Mode, ScaleMode};
+use level::Level;
+use level_select::{Choice, LevelSelect};
+use net::{Server, Session, Spectator};
+use options::{Batch, Options, Screenshot, USAGE};
+use renderer::{Canvas, GlRenderer, Renderer};
+use replay::Replay;
+
+/// The logical resolution of the game
+const RESOLUTION: [u32; 2] = [1024, 600];
+
+/// The amount of updates per second
+const UPS: u64 = 60;
+
+/// The font used to draw text
+const FONT: &'static str = "resources/FiraMono-Bold.ttf";
 
-use drawing::Size;
-use game::Game;
+/// The file used by the quicksave and quickload keys
+const QUICKSAVE: &'static str = "quicksave.json";
+
+/// The file the level editor saves to, unless another one is given with `--level`
+const LEVEL: &'static str = "level.toml";
+
+/// The file describing the levels of the campaign
+const CAMPAIGN: &'static str = "resources/campaign.toml";
+
+/// The file keeping the stars earned in the campaign
+const PROGRESS: &'static str = "progress.toml";
 
 fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n\n{}", e, USAGE);
+            return;
+        }
+    };
+
+    if let Some(ref directory) = options.export_sounds {
+        if let Err(e) = synth::export(directory) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(ref screenshot) = options.screenshot {
+        if let Err(e) = take_screenshot(screenshot) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(port) = options.server {
+        if let Err(e) = serve(&options, port) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(ref address) = options.spectate {
+        if let Err(e) = spectate(address) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(ref batch) = options.batch {
+        if let Err(e) = run_batch(&options, batch) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if options.environment {
+        // The standard output belongs to the agent, so errors go elsewhere
+        let (stdin, stdout) = (io::stdin(), io::stdout());
+        let result = Environment::new(options.mode)
+                         .and_then(|mut env| environment::run(&mut env, stdin.lock(), stdout.lock()));
+        if let Err(e) = result {
+            let _ = writeln!(io::stderr(), "{}", e);
+        }
+        return;
+    }
+
+    play(&options);
+}
+
+/// A game played on this computer or online, or the demo of the title screen
+enum Driver {
+    Local(Game),
+    Online(Session),
+    /// Every player is flown by the autopilot, waiting for someone to start a game
+    Demo(Game)
+}
+
+impl Driver {
+    /// Starts the game described by the options
+    fn start(options: &Options, seed: u64) -> Result<Driver, String> {
+        if let Some(port) = options.host {
+            let socket = try!(net::bind(port));
+            println!("Waiting for a player to join on port {}...", port);
+            return Session::host(socket, resolution(), options.mode, seed, options.delay, UPS).map(Driver::Online);
+        }
+
+        if let Some(ref address) = options.join {
+            let socket = try!(net::bind(0));
+            return Session::join(socket, resolution(), &address[..], UPS).map(Driver::Online);
+        }
+
+        let mut game = try!(new_game(options, seed));
+        if let Some(ref path) = options.load {
+            try!(save::load(&mut game, path));
+        }
+        Ok(Driver::Local(game))
+    }
+
+    /// Returns the game, as seen by the local player
+    fn game(&mut self) -> &mut Game {
+        match *self {
+            Driver::Local(ref mut game) | Driver::Demo(ref mut game) => game,
+            Driver::Online(ref mut session) => session.game()
+        }
+    }
+
+    fn is_demo(&self) -> bool {
+        match *self {
+            Driver::Demo(_) => true,
+            _ => false
+        }
+    }
+
+    fn is_local(&self) -> bool {
+        match *self {
+            Driver::Local(_) => true,
+            _ => false
+        }
+    }
+
+    fn key_press(&mut self, key: Key) {
+        match *self {
+            Driver::Local(ref mut game) => game.key_press(key),
+            Driver::Online(ref mut session) => session.key_press(key),
+            Driver::Demo(_) => {}
+        }
+    }
+
+    fn key_release(&mut self, key: Key) {
+        match *self {
+            Driver::Local(ref mut game) => game.key_release(key),
+            Driver::Online(ref mut session) => session.key_release(key),
+            Driver::Demo(_) => {}
+        }
+    }
+
+    /// Saves the game to the quicksave file
+    fn quicksave(&mut self) -> Result<(), String> {
+        match *self {
+            Driver::Local(ref game) => save::save(game, QUICKSAVE),
+            Driver::Online(_) => Err("Online games can't be saved".to_string()),
+            Driver::Demo(_) => Err("The demo can't be saved".to_string())
+        }
+    }
+
+    /// Replaces the game with the one in the quicksave file
+    fn quickload(&mut self) -> Result<(), String> {
+        match *self {
+            Driver::Local(ref mut game) => save::load(game, QUICKSAVE),
+            Driver::Online(_) => Err("Online games can't be loaded".to_string()),
+            Driver::Demo(_) => Err("Start a game before loading one".to_string())
+        }
+    }
+
+    /// Updates the game. Online games always advance a whole frame, so both
+    /// players get the same results
+    fn update(&mut self, dt: f64) {
+        match *self {
+            Driver::Local(ref mut game) => game.update(dt),
+            Driver::Online(ref mut session) => session.update(),
+            Driver::Demo(ref mut game) => {
+                for i in 0..game.mode().players() {
+                    let actions = autopilot::actions(game.world(), i);
+                    game.set_actions(i, actions);
+                }
+                game.update(dt);
+            }
+        }
+    }
+}
+
+/// Plays the game in a window
+fn play(options: &Options) {
     // Initialization stuff
     let opengl = OpenGL::_3_2;
 
+    // The game object. Local games start with the demo on the title screen,
+    // unless they must start right away
+    let seed = options.seed.unwrap_or_else(|| rand::random());
+    let title = options.host.is_none() && options.join.is_none() && options.load.is_none() && options.record.is_none();
+    let start = if title {
+        new_game(options, rand::random()).map(Driver::Demo)
+    } else {
+        Driver::start(options, seed)
+    };
+    let mut driver = match start {
+        Ok(driver) => driver,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    // The replay, if we are recording one
+    let mut replay = options.record.as_ref().map(|_| {
+        let mut replay = Replay::new(seed, UPS, options.mode);
+        if options.level.is_some() {
+            replay.level = Some(Level::from_world(driver.game().world()));
+        }
+        replay
+    });
+
+    // The sound effects and the music
+    let mut audio = Audio::new();
+
+    // The level editor, while it's open
+    let mut editor: Option<Editor> = None;
+
+    // The campaign, the stars earned in it, the level select screen (while
+    // it's open) and the level being played
+    let campaign = Campaign::load(CAMPAIGN).unwrap_or_else(|e| {
+        println!("{}", e);
+        Campaign { stages: vec![] }
+    });
+    let mut progress = Progress::load(PROGRESS).unwrap_or_else(|e| {
+        println!("{}", e);
+        Progress::default()
+    });
+    let mut select: Option<LevelSelect> = None;
+    let mut attempt: Option<Attempt> = None;
+
+    // Toggling fullscreen requires a new window, so we loop until the window
+    // is closed without asking for a toggle
+    let mut fullscreen = false;
+    loop {
+        let window: GlutinWindow =
+            WindowSettings::new("Rocket", RESOLUTION)
+                .exit_on_esc(true)
+                .fullscreen(fullscreen)
+                .opengl(opengl)
+                .samples(8)
+                .into();
+
+        // The OpenGL resources belong to the window, so they are created again as well
+        let mut gl = GlGraphics::new(opengl);
+        let mut font = GlyphCache::new(&Path::new(FONT)).unwrap();
+
+        let size = window.size();
+        let mut screen = Size::new(size.width as f64, size.height as f64);
+        driver.game().resize(screen.width, screen.height);
+
+        let mut toggle_fullscreen = false;
+
+        // Event handling
+        for e in window.events().ups(UPS).max_fps(60) {
+            match e {
+                Event::Input(Input::Press(Button::Keyboard(Key::F11))) => {
+                    toggle_fullscreen = true;
+                    break;
+                }
+
+                Event::Input(Input::Press(Button::Keyboard(Key::F2))) => {
+                    if editor.is_some() {
+                        editor = None;
+                    } else if !driver.is_local() {
+                        println!("Levels can only be edited in local games");
+                    } else if attempt.is_some() {
+                        println!("Levels of the campaign can't be edited");
+                    } else if replay.is_some() {
+                        println!("Levels can't be edited while recording a replay");
+                    } else {
+                        let path = options.level.as_ref().map_or(LEVEL, |path| &path[..]);
+                        editor = Some(Editor::new(path, driver.game()));
+                    }
+                }
+
+                // While editing, the keys belong to the editor
+                Event::Input(Input::Press(Button::Keyboard(key))) if editor.is_some() => {
+                    if let Some(ref mut editor) = editor {
+                        editor.key_press(key, driver.game());
+                    }
+                }
+
+                // While choosing a level, the keys belong to the level select screen
+                Event::Input(Input::Press(Button::Keyboard(key))) if select.is_some() => {
+                    let choice = select.as_mut().and_then(|select| select.key_press(key, &campaign, &progress));
+                    match choice {
+                        Some(Choice::Play(stage)) => match campaign_game(options, &campaign.stages[stage]) {
+                            Ok(game) => {
+                                attempt = Some(Attempt::new(stage, &game));
+                                driver = Driver::Local(game);
+                                driver.game().resize(screen.width, screen.height);
+                                select = None;
+                            }
+                            Err(e) => println!("{}", e)
+                        },
+                        Some(Choice::Back) => select = None,
+                        None => {}
+                    }
+                }
+
+                Event::Input(Input::Press(Button::Mouse(button))) => {
+                    if let Some(ref mut editor) = editor {
+                        editor.mouse_press(button, driver.game());
+                    }
+                }
+
+                Event::Input(Input::Move(Motion::MouseCursor(x, y))) => {
+                    if let Some(ref mut editor) = editor {
+                        editor.mouse_move(x, y);
+                    }
+                }
+
+                Event::Input(Input::Press(Button::Keyboard(key))) => {
+                    match key {
+                        Key::Return if driver.is_demo() => match Driver::start(options, seed) {
+                            Ok(game) => {
+                                driver = game;
+                                driver.game().resize(screen.width, screen.height);
+                            }
+                            Err(e) => println!("{}", e)
+                        },
+                        Key::C if driver.is_demo() => if campaign.stages.is_empty() {
+                            println!("There is no campaign to play");
+                        } else {
+                            select = Some(LevelSelect::new(&campaign, &progress));
+                        },
+                        // Once a level of the campaign is over, go back to choosing one
+                        Key::Return if attempt.as_ref().map_or(false, |a| a.outcome.is_some()) => {
+                            match new_game(options, rand::random()) {
+                                Ok(game) => {
+                                    driver = Driver::Demo(game);
+                                    driver.game().resize(screen.width, screen.height);
+                                    attempt = None;
+                                    select = Some(LevelSelect::new(&campaign, &progress));
+                                }
+                                Err(e) => println!("{}", e)
+                            }
+                        }
+                        Key::M => audio.toggle_mute(),
+                        Key::Minus => audio.change_volume(-1),
+                        Key::Equals => audio.change_volume(1),
+                        Key::F5 if attempt.is_some() => println!("Games can't be saved while playing the campaign"),
+                        Key::F5 => match driver.quicksave() {
+                            Ok(()) => println!("Game saved to {}", QUICKSAVE),
+                            Err(e) => println!("{}", e)
+                        },
+                        // A replay can't follow a game that jumps to another state
+                        Key::F9 if replay.is_some() => println!("Games can't be loaded while recording a replay"),
+                        Key::F9 if attempt.is_some() => println!("Games can't be loaded while playing the campaign"),
+                        Key::F9 => if let Err(e) = driver.quickload() {
+                            println!("{}", e);
+                        },
+                        Key::Backspace if replay.is_some() => println!("Time can't be rewound while recording a replay"),
+                        _ => driver.key_press(key)
+                    }
+                }
+
+                Event::Input(Input::Release(Button::Keyboard(key))) => {
+                    driver.key_release(key);
+                }
+
+                Event::Input(Input::Resize(width, height)) => {
+                    screen = Size::new(width as f64, height as f64);
+                    driver.game().resize(screen.width, screen.height);
+                }
+
+                Event::Render(args) => {
+                    gl.draw(args.viewport(), |c, g| {
+                        let mut renderer = GlRenderer::new(g, &mut font, c.draw_state.clone());
+                        driver.game().render(c, &mut renderer);
+                        if driver.is_demo() && select.is_none() {
+                            render_title(c, &mut renderer, &screen);
+                        }
+                        if let Some(ref select) = select {
+                            select.render(c, &mut renderer, &campaign, &progress, &screen);
+                        }
+                        if let Some(ref attempt) = attempt {
+                            attempt.render(c, &mut renderer, &campaign.stages[attempt.stage], &screen);
+                        }
+                        if let Some(ref editor) = editor {
+                            editor.render(c, &mut renderer, driver.game(), &screen);
+                        }
+                    });
+                }
+
+                // The game stands still while editing and once a level of the campaign is over
+                Event::Update(_) if editor.is_some() || attempt.as_ref().map_or(false, |a| a.outcome.is_some()) => {
+                    audio.set_thrust(false);
+                }
+
+                Event::Update(args) => {
+                    if let Some(ref mut replay) = replay {
+                        replay.record(driver.game().actions());
+                    }
+                    driver.update(args.dt);
+                    if let Err(e) = driver.game().reload_effects(args.dt) {
+                        println!("{}", e);
+                    }
+                    if let Some(ref mut attempt) = attempt {
+                        let stage = attempt.stage;
+                        if let Some(Outcome::Complete(stars)) = attempt.update(driver.game(), &campaign.stages[stage]) {
+                            progress.record(stage, stars);
+                            if let Err(e) = progress.save(PROGRESS) {
+                                println!("{}", e);
+                            }
+                        }
+                    }
+                    let game = driver.game();
+                    audio.update(args.dt, game.sounds());
+                    audio.set_thrust(game.actions().iter().any(|a| a.boost));
+                }
+
+                _ => {}
+            }
+        }
+
+        if !toggle_fullscreen {
+            break;
+        }
+        fullscreen = !fullscreen;
+    }
+
+    if let (Some(replay), Some(path)) = (replay, options.record.as_ref()) {
+        if let Err(e) = replay.save(path) {
+            println!("{}", e);
+        }
+    }
+}
+
+/// Draws the title screen over the demo
+fn render_title<R: Renderer>(c: Context, r: &mut R, screen: &Size) {
+    let (x, y) = (screen.width / 2.0, screen.height / 2.0);
+    game::render_centered(r, color::ORANGE, 64, "ROCKET", c.trans(x, y - 20.0));
+    game::render_centered(r, color::ORANGE, 22, "Press Enter to play", c.trans(x, y + 30.0));
+    game::render_centered(r, color::ORANGE, 22, "Press C for the campaign", c.trans(x, y + 60.0));
+}
+
+/// Runs a game flown by the autopilot without a window, streaming it to spectators
+fn serve(options: &Options, port: u16) -> Result<(), String> {
+    let seed = options.seed.unwrap_or_else(|| rand::random());
+    let mut driver = Driver::Demo(try!(new_game(options, seed)));
+    let mut server = try!(net::bind(port).and_then(Server::new));
+    println!("Streaming a game on port {}", port);
+
+    let mut spectators = 0;
+    loop {
+        driver.update(1.0 / UPS as f64);
+        server.update(driver.game());
+
+        if server.spectators() != spectators {
+            spectators = server.spectators();
+            println!("Spectators watching: {}", spectators);
+        }
+
+        // Play in real time, so the spectators see the game at its normal speed
+        thread::sleep(Duration::from_millis(1000 / UPS));
+    }
+}
+
+/// Plays many games without a window and saves statistics about them
+fn run_batch(options: &Options, batch: &Batch) -> Result<(), String> {
+    let (pilot, mode, ups, seed, level) = match batch.inputs {
+        Some(ref path) => {
+            // The first game is the one that was recorded, unless another seed is given
+            let replay = try!(Replay::load(path));
+            let (mode, ups, seed) = (replay.mode, replay.ups, options.seed.unwrap_or(replay.seed));
+            let level = replay.level.clone();
+            (batch::Pilot::Replay(replay), mode, ups, seed, level)
+        }
+        None => {
+            let level = match options.level {
+                Some(ref path) => Some(try!(Level::load(path))),
+                None => None
+            };
+            (batch::Pilot::Autopilot, options.mode, UPS, options.seed.unwrap_or(0), level)
+        }
+    };
+
+    println!("Playing {} games...", batch.games);
+    let records = try!(batch::run(batch::Settings {
+        games: batch.games,
+        seed: seed,
+        mode: mode,
+        ups: ups,
+        duration: batch.duration,
+        threads: batch.threads,
+        pilot: pilot,
+        level: level
+    }));
+    try!(batch::save(&records, &batch.output));
+    println!("{}\nThe statistics of each player were saved to {}", batch::summary(&records), batch.output);
+    Ok(())
+}
+
+/// Watches the games of a server in a window
+fn spectate(address: &str) -> Result<(), String> {
+    let mut spectator = try!(net::bind(0).and_then(|socket| Spectator::new(socket, address, UPS)));
+
+    let opengl = OpenGL::_3_2;
     let window: GlutinWindow =
-        WindowSettings::new("Rocket", [1024, 600])
+        WindowSettings::new("Rocket (spectating)", RESOLUTION)
             .exit_on_esc(true)
             .opengl(opengl)
             .samples(8)
             .into();
-
     let mut gl = GlGraphics::new(opengl);
+    let mut font = GlyphCache::new(&Path::new(FONT)).unwrap();
+    let mut screen = resolution();
 
-    // The game object
-    let mut game = Game::new(Size::new(1024.0, 600.0));
-
-    // Event handling
-    for e in window.events().ups(60).max_fps(60) {
+    for e in window.events().ups(UPS).max_fps(60) {
         match e {
-            Event::Input(Input::Press(Button::Keyboard(key))) => {
-                game.key_press(key);
-            }
-
-            Event::Input(Input::Release(Button::Keyboard(key))) => {
-                game.key_release(key);
+            Event::Input(Input::Resize(width, height)) => {
+                screen = Size::new(width as f64, height as f64);
             }
 
             Event::Render(args) => {
-                gl.draw(args.viewport(), |c, g| game.render(c, g));
+                gl.draw(args.viewport(), |c, g| {
+                    let mut renderer = GlRenderer::new(g, &mut font, c.draw_state.clone());
+                    spectator.render(c, &mut renderer, &screen);
+                });
             }
 
-            Event::Update(args) => {
-                game.update(args.dt);
-            }
+            Event::Update(args) => spectator.update(args.dt),
 
             _ => {}
         }
     }
+
+    Ok(())
+}
+
+/// Plays a replay without a window and saves one of its frames as an image
+fn take_screenshot(screenshot: &Screenshot) -> Result<(), String> {
+    let replay = try!(Replay::load(&screenshot.replay));
+    let mut game = try!(replay_game(&replay));
+    replay.play(&mut game, screenshot.frame);
+
+    let mut canvas = try!(Canvas::with_font(RESOLUTION[0], RESOLUTION[1], FONT));
+    game.render(Context::abs(RESOLUTION[0] as f64, RESOLUTION[1] as f64), &mut canvas);
+    canvas.save_png(&screenshot.image)
+}
+
+/// Returns a new game with the given seed, in the level given in the options (if any)
+fn new_game(options: &Options, seed: u64) -> Result<Game, String> {
+    let mut game = try!(Game::new(resolution(), options.scale_mode, options.mode, seed));
+    if let Some(ref path) = options.level {
+        game.set_level(&try!(Level::load(path)));
+    }
+    Ok(game)
+}
+
+/// Returns a new game in the given level of the campaign
+fn campaign_game(options: &Options, stage: &Stage) -> Result<Game, String> {
+    // The campaign is played against the invaders, so versus mode becomes a single player game
+    let mode = if options.mode.is_versus() { Mode::Single } else { options.mode };
+    let mut game = try!(Game::new(resolution(), options.scale_mode, mode, rand::random()));
+    // Rewinding would make the objectives too easy
+    game.disable_rewind();
+    stage.start(&mut game);
+    Ok(game)
+}
+
+/// Returns a new game like the one recorded in the replay
+fn replay_game(replay: &Replay) -> Result<Game, String> {
+    let mut game = try!(Game::new(resolution(), ScaleMode::Letterbox, replay.mode, replay.seed));
+    if let Some(ref level) = replay.level {
+        game.set_level(level);
+    }
+    Ok(game)
+}
+
+/// Returns the logical resolution of the game
+fn resolution() -> Size {
+    Size::new(RESOLUTION[0] as f64, RESOLUTION[1] as f64)
 }
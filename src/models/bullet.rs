@@ -1,35 +1,65 @@
-use drawing::color;
+use drawing::{color, Point};
 use super::Vector;
 use traits::{Advance, Collide, Position};
 
-use graphics::{Context, Ellipse};
-use opengl_graphics::GlGraphics;
+use graphics::Context;
+use renderer::Renderer;
+
+/// The speed of bullets, in pixels per second
+const SPEED: f64 = 500.0;
 
 /// Bullets are spawned when the player shoots
 ///
 /// When an enemy is reached by a bullet, it will explode
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct Bullet {
-    vector: Vector
+    pub vector: Vector,
+    /// The index of the player that fired the bullet
+    pub owner: usize
 }
 
 derive_position_direction!(Bullet);
 
 impl Bullet {
-    /// Create a bullet with the given vector
-    pub fn new(vector: Vector) -> Bullet {
-        Bullet { vector: vector }
+    /// Create a bullet with the given vector, fired by the given player
+    pub fn new(vector: Vector, owner: usize) -> Bullet {
+        Bullet { vector: vector, owner: owner }
     }
 
     /// Draw the bullet
-    pub fn draw(&self, c: &Context, gl: &mut GlGraphics) {
-        Ellipse::new(color::BLUE).draw(
-            [self.x() - self.radius(), self.y() - self.radius(), self.diameter(), self.diameter()],
-            &c.draw_state, c.transform, gl);
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        r.ellipse(color::BLUE,
+                  [self.x() - self.radius(), self.y() - self.radius(), self.diameter(), self.diameter()],
+                  c.transform);
     }
 
     /// Update the bullet's position
-    pub fn update(&mut self, units: f64) {
-        self.advance(units);
+    ///
+    /// `force` is the acceleration caused by the environment, which bends the
+    /// path of the bullet without changing its speed
+    pub fn update(&mut self, dt: f64, force: &Point) {
+        if force.x != 0.0 || force.y != 0.0 {
+            let velocity = Point::new(self.direction().cos() * SPEED + force.x * dt,
+                                      self.direction().sin() * SPEED + force.y * dt);
+            *self.direction_mut() = velocity.y.atan2(velocity.x);
+        }
+        self.advance(dt * SPEED);
+    }
+
+    /// Moves the bullet by the given offset, out of whatever it hit, and
+    /// bounces it off in the direction of the offset
+    pub fn ricochet(&mut self, push: &Point) {
+        *self.x_mut() += push.x;
+        *self.y_mut() += push.y;
+
+        // Reflect the direction on the surface that was hit
+        let length = (push.x * push.x + push.y * push.y).sqrt();
+        if length > 0.0 {
+            let (nx, ny) = (push.x / length, push.y / length);
+            let (dx, dy) = (self.direction().cos(), self.direction().sin());
+            let dot = dx * nx + dy * ny;
+            *self.direction_mut() = (dy - 2.0 * dot * ny).atan2(dx - 2.0 * dot * nx);
+        }
     }
 }
 
@@ -0,0 +1,65 @@
+use drawing::color::Color;
+use super::{Particle, ParticlePool, Vector};
+
+/// An `Emitter` describes how a burst of particles is spawned
+///
+/// The particles are fired in `count` directions, evenly distributed across
+/// the `spread` angle. In each direction, `layers` particles are fired, with
+/// lifetimes and speeds evenly distributed across the given ranges. This is
+/// what gives explosions their ring-like look
+///
+/// Emitters are usually defined in the effects file (see `effects::Effects`)
+#[derive(Clone, RustcDecodable)]
+pub struct Emitter {
+    /// The amount of directions in which particles are fired
+    pub count: u32,
+    /// The amount of particles fired in each direction
+    pub layers: u32,
+    /// The angle in degrees covered by the directions
+    pub spread: f64,
+    /// The shortest and longest lifetimes, in seconds
+    pub lifetime: [f64; 2],
+    /// The speeds of the shortest and longest living particles
+    pub speed: [f64; 2],
+    /// The downwards acceleration applied to the particles
+    pub gravity: f64,
+    /// The colors of the particles at birth and at death
+    pub colors: [Color; 2],
+    /// The radius of the particles at birth and at death
+    pub sizes: [f64; 2]
+}
+
+impl Emitter {
+    /// Spawns the particles in the pool, using the given vector as the origin
+    ///
+    /// The direction of the vector is used as the center of the spread
+    pub fn emit(&self, pool: &mut ParticlePool, origin: Vector) {
+        let spread = self.spread.to_radians();
+        for i in 0..self.count {
+            // Spread the directions evenly, centered on the origin's direction
+            let fraction = (i as f64 + 0.5) / self.count as f64;
+            let direction = origin.direction - spread / 2.0 + spread * fraction;
+
+            for layer in 0..self.layers {
+                let t = if self.layers > 1 { layer as f64 / (self.layers - 1) as f64 } else { 0.0 };
+                let lifetime = lerp(self.lifetime, t);
+
+                pool.spawn(Particle {
+                    vector: Vector::new(origin.position.clone(), direction),
+                    ttl: lifetime,
+                    lifetime: lifetime,
+                    speed: lerp(self.speed, t),
+                    gravity: self.gravity,
+                    fall_speed: 0.0,
+                    colors: self.colors,
+                    sizes: self.sizes
+                });
+            }
+        }
+    }
+}
+
+/// Linearly interpolates between the two values of the range
+fn lerp(range: [f64; 2], t: f64) -> f64 {
+    range[0] + (range[1] - range[0]) * t
+}
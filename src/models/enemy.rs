@@ -1,38 +1,181 @@
-use drawing::Point;
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use drawing::{color, Point};
+use drawing::color::Color;
 use super::Vector;
+use super::steering::Weights;
 use traits::{Advance, Collide, Position};
 
-use graphics::{Context, Ellipse};
-use opengl_graphics::GlGraphics;
+use graphics::Context;
+use renderer::Renderer;
 
-/// Enemies follow the player in order to cause a collision and let him explode 
+/// Enemies follow the player in order to cause a collision and let him explode
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct Enemy {
-    vector: Vector
+    pub vector: Vector,
+    pub kind: Kind,
+    /// The remaining seconds the enemy flies straight ahead, as part of a
+    /// formation, before it starts steering
+    pub charge: f64,
+    /// The amount of hits the enemy takes before it explodes
+    pub health: u32
+}
+
+/// The kinds of enemies, which differ in how they move
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Kind {
+    /// Heads straight for the nearest player, keeping some room from the others
+    Chaser,
+    /// Flies in flocks that drift towards the players
+    Swarmer,
+    /// Heads for where the nearest player is going and dodges bullets
+    Hunter,
+    /// A large and slow enemy that takes many hits, only sent by waves
+    Boss
+}
+
+/// Enemies fly this many times faster while charging
+const CHARGE_SPEEDUP: f64 = 1.5;
+
+/// Every kind of enemy, in the order used to identify them in snapshots
+pub const KINDS: [Kind; 4] = [Kind::Chaser, Kind::Swarmer, Kind::Hunter, Kind::Boss];
+
+impl Kind {
+    /// Returns a random kind. Chasers are the most common and hunters the rarest.
+    /// Bosses are never chosen
+    pub fn random<R: Rng>(rng: &mut R) -> Kind {
+        match rng.gen_range(0, 20) {
+            0...11 => Kind::Chaser,
+            12...16 => Kind::Swarmer,
+            _ => Kind::Hunter
+        }
+    }
+
+    /// Returns how much each steering behaviour counts for this kind
+    pub fn weights(&self) -> Weights {
+        match *self {
+            Kind::Chaser => Weights { separation: 1.5, alignment: 0.0, cohesion: 0.0, seek: 1.0, flee: 0.0, pursue: 0.0,
+                                      avoid: 2.0 },
+            Kind::Swarmer => Weights { separation: 1.5, alignment: 1.0, cohesion: 0.8, seek: 0.6, flee: 0.0, pursue: 0.0,
+                                       avoid: 2.0 },
+            Kind::Hunter => Weights { separation: 1.0, alignment: 0.0, cohesion: 0.0, seek: 0.0, flee: 1.5, pursue: 1.0,
+                                      avoid: 2.5 },
+            Kind::Boss => Weights { separation: 0.5, alignment: 0.0, cohesion: 0.0, seek: 1.0, flee: 0.0, pursue: 0.0,
+                                    avoid: 2.0 }
+        }
+    }
+
+    /// Returns the speed of this kind, in pixels per second
+    pub fn speed(&self) -> f64 {
+        match *self {
+            Kind::Chaser => 100.0,
+            Kind::Swarmer => 120.0,
+            Kind::Hunter => 90.0,
+            Kind::Boss => 60.0
+        }
+    }
+
+    /// Returns how fast this kind turns, in radians per second
+    fn turn_rate(&self) -> f64 {
+        match *self {
+            Kind::Chaser => 6.0,
+            Kind::Swarmer => 4.0,
+            Kind::Hunter => 3.0,
+            Kind::Boss => 1.5
+        }
+    }
+
+    /// Returns the color of this kind
+    pub fn color(&self) -> Color {
+        match *self {
+            Kind::Chaser => color::YELLOW,
+            Kind::Swarmer => color::ORANGE,
+            Kind::Hunter => color::PINK,
+            Kind::Boss => color::WHITE
+        }
+    }
+
+    /// Returns the amount of hits an enemy of this kind takes before it explodes
+    pub fn health(&self) -> u32 {
+        match *self {
+            Kind::Boss => 25,
+            _ => 1
+        }
+    }
+
+    /// Returns the points a player gets for shooting down an enemy of this kind
+    pub fn points(&self) -> u32 {
+        match *self {
+            Kind::Boss => 250,
+            _ => 10
+        }
+    }
 }
 
 derive_position_direction!(Enemy);
 
 impl Enemy {
-    /// Create a enemy with the given vector
-    pub fn new(vector: Vector) -> Enemy {
-        Enemy { vector: vector }
+    /// Create a enemy of the given kind with the given vector
+    pub fn new(vector: Vector, kind: Kind) -> Enemy {
+        Enemy { vector: vector, kind: kind, charge: 0.0, health: kind.health() }
     }
 
-    /// Draw the enemy
-    pub fn draw(&self, c: &Context, gl: &mut GlGraphics) {
-        Ellipse::new([1.0, 1.0, 0.0, 1.0]).draw(
-            [self.x() - 10.0, self.y() - 10.0, 20.0, 20.0],
-            &c.draw_state, c.transform, gl);
+    /// Draw the enemy. Enemies that take more than one hit have a bar above
+    /// them showing their remaining health
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        r.ellipse(self.kind.color(),
+                  [self.x() - self.radius(), self.y() - self.radius(), self.diameter(), self.diameter()],
+                  c.transform);
+
+        let full = self.kind.health();
+        if full > 1 {
+            let width = self.diameter() * self.health as f64 / full as f64;
+            r.rectangle(color::RED, [self.x() - self.radius(), self.y() - self.radius() - 10.0, width, 4.0], c.transform);
+        }
     }
 
     /// Update the enemy
-    pub fn update(&mut self, speed: f64, player_position: Point) {
-        // Point to the player
-        self.point_to(player_position);
-        self.advance(speed);
+    ///
+    /// The enemy turns towards the direction it is steering in, as fast as
+    /// its kind allows, and moves forward. Without a direction (when there
+    /// are no players to chase) it stays still. While charging, it ignores
+    /// the steering and flies straight ahead
+    pub fn update(&mut self, dt: f64, steering: Option<Point>) {
+        if self.charge > 0.0 {
+            self.charge -= dt;
+            let speed = self.kind.speed() * CHARGE_SPEEDUP;
+            self.advance(speed * dt);
+            return;
+        }
+
+        let steering = match steering {
+            Some(steering) => steering,
+            None => return
+        };
+
+        if steering.x != 0.0 || steering.y != 0.0 {
+            let mut turn = (steering.y.atan2(steering.x) - self.direction()) % (2.0 * PI);
+            if turn > PI {
+                turn -= 2.0 * PI;
+            } else if turn < -PI {
+                turn += 2.0 * PI;
+            }
+            let max_turn = self.kind.turn_rate() * dt;
+            *self.direction_mut() += turn.max(-max_turn).min(max_turn);
+        }
+        let speed = self.kind.speed();
+        self.advance(speed * dt);
     }
 }
 
 impl Collide for Enemy {
-    fn radius(&self) -> f64 { 10.0 }
+    fn radius(&self) -> f64 {
+        match self.kind {
+            Kind::Swarmer => 7.0,
+            Kind::Boss => 30.0,
+            _ => 10.0
+        }
+    }
 }
@@ -0,0 +1,58 @@
+use std::f64;
+
+use drawing::{color, Point};
+
+use graphics::{Context, Transformed};
+use renderer::Renderer;
+
+/// The width of a laser beam, in pixels
+const WIDTH: f64 = 4.0;
+
+/// A laser barrier, placed in the world by a level, that destroys anything
+/// crossing its beam
+///
+/// The beam sweeps back and forth: it moves smoothly by `travel` and back
+/// again every `period` seconds
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Laser {
+    /// One end of the beam, at the start of the sweep
+    pub from: Point,
+    /// The other end of the beam, at the start of the sweep
+    pub to: Point,
+    /// How far the beam moves from its starting place
+    pub travel: Point,
+    /// The seconds the beam takes to go and come back, or zero for a beam that stays still
+    pub period: f64
+}
+
+impl Laser {
+    /// Create a laser between the given points, sweeping by `travel` every `period` seconds
+    #[cfg(test)]
+    pub fn new(from: Point, to: Point, travel: Point, period: f64) -> Laser {
+        Laser { from: from, to: to, travel: travel, period: period }
+    }
+
+    /// Returns the ends of the beam at the given time
+    pub fn ends(&self, time: f64) -> (Point, Point) {
+        let t = if self.period > 0.0 { (1.0 - (time / self.period * 2.0 * f64::consts::PI).cos()) / 2.0 } else { 0.0 };
+        let offset = Point::new(self.travel.x * t, self.travel.y * t);
+        (self.from.clone().translate(&offset), self.to.clone().translate(&offset))
+    }
+
+    /// Returns true if a circle with the given center and radius touches the beam at the given time
+    pub fn hits(&self, time: f64, center: &Point, radius: f64) -> bool {
+        let (a, b) = self.ends(time);
+        let reach = radius + WIDTH / 2.0;
+        center.closest_on_segment(&a, &b).squared_distance_to(center) < reach * reach
+    }
+
+    /// Draw the beam as it is at the given time
+    pub fn draw<R: Renderer>(&self, time: f64, c: &Context, r: &mut R) {
+        let (a, b) = self.ends(time);
+        let length = a.squared_distance_to(&b).sqrt();
+        let transform = c.transform.trans(a.x, a.y).rot_rad((b.y - a.y).atan2(b.x - a.x));
+        r.rectangle(color::RED, [0.0, -WIDTH / 2.0, length, WIDTH], transform);
+        r.ellipse(color::ORANGE, [-WIDTH, -WIDTH, WIDTH * 2.0, WIDTH * 2.0], transform);
+        r.ellipse(color::ORANGE, [length - WIDTH, -WIDTH, WIDTH * 2.0, WIDTH * 2.0], transform);
+    }
+}
@@ -3,14 +3,29 @@
 mod vector;
 
 mod bullet;
-mod enemy;
+mod emitter;
+pub mod enemy;
+mod laser;
+mod obstacle;
 mod particle;
-mod player;
+mod particle_pool;
+mod pickup;
+pub mod player;
+pub mod steering;
+mod telegraph;
+mod well;
 mod world;
 
 pub use self::bullet::Bullet;
+pub use self::emitter::Emitter;
 pub use self::enemy::Enemy;
+pub use self::laser::Laser;
+pub use self::obstacle::{Obstacle, Shape};
 pub use self::particle::Particle;
+pub use self::particle_pool::ParticlePool;
+pub use self::pickup::Pickup;
 pub use self::player::Player;
+pub use self::telegraph::Telegraph;
 pub use self::vector::Vector;
+pub use self::well::Well;
 pub use self::world::World;
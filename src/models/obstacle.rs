@@ -0,0 +1,171 @@
+use std::f64;
+
+use drawing::{color, Point, Size};
+use traits::Position;
+
+use graphics::{Context, Transformed};
+use renderer::Renderer;
+
+/// A static obstacle, placed in the world by a level, that blocks the
+/// players, the enemies and the bullets
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Obstacle {
+    pub position: Point,
+    pub shape: Shape,
+    /// True if bullets bounce off the obstacle instead of being stopped by it
+    pub ricochet: bool
+}
+
+/// The shape of an obstacle, around its position
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub enum Shape {
+    /// A circle of the given radius
+    Circle(f64),
+    /// A rectangle of the given size, centered on the position
+    Rectangle(Size),
+    /// A convex polygon, with its corners relative to the position
+    Polygon(Vec<Point>)
+}
+
+derive_position!(Obstacle);
+
+impl Obstacle {
+    /// Create an obstacle of the given shape at the given position
+    pub fn new(position: Point, shape: Shape, ricochet: bool) -> Obstacle {
+        Obstacle { position: position, shape: shape, ricochet: ricochet }
+    }
+
+    /// Returns the radius of the smallest circle around the position that
+    /// contains the whole obstacle
+    pub fn reach(&self) -> f64 {
+        match self.shape {
+            Shape::Circle(radius) => radius,
+            Shape::Rectangle(ref size) => (size.width * size.width + size.height * size.height).sqrt() / 2.0,
+            Shape::Polygon(ref corners) => corners.iter().map(|c| (c.x * c.x + c.y * c.y).sqrt()).fold(0.0, f64::max)
+        }
+    }
+
+    /// Returns how far a circle with the given center and radius must move to
+    /// stop overlapping the obstacle, or `None` if they don't overlap
+    pub fn push_out(&self, center: &Point, radius: f64) -> Option<Point> {
+        let local = Point::new(center.x - self.position.x, center.y - self.position.y);
+        let reach = self.reach() + radius;
+        if local.x * local.x + local.y * local.y >= reach * reach {
+            return None;
+        }
+
+        match self.shape {
+            Shape::Circle(r) => push_out_of_circle(&local, r, radius),
+            Shape::Rectangle(ref size) => {
+                let (w, h) = (size.width / 2.0, size.height / 2.0);
+                let corners = [Point::new(-w, -h), Point::new(w, -h), Point::new(w, h), Point::new(-w, h)];
+                push_out_of_polygon(&local, &corners, radius)
+            }
+            Shape::Polygon(ref corners) => push_out_of_polygon(&local, corners, radius)
+        }
+    }
+
+    /// Draw the obstacle. The ones bullets bounce off have a different color
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        let color = if self.ricochet { color::VIOLET } else { color::GREY };
+        let (x, y) = (self.x(), self.y());
+        match self.shape {
+            Shape::Circle(radius) => r.ellipse(color, [x - radius, y - radius, radius * 2.0, radius * 2.0], c.transform),
+            Shape::Rectangle(ref size) => {
+                r.rectangle(color, [x - size.width / 2.0, y - size.height / 2.0, size.width, size.height], c.transform)
+            }
+            Shape::Polygon(ref corners) => {
+                let corners: Vec<_> = corners.iter().map(|p| [p.x, p.y]).collect();
+                r.polygon(color, &corners, c.transform.trans(x, y));
+            }
+        }
+    }
+}
+
+/// Returns how far a circle must move to leave a circle of radius `r` centered on the origin
+fn push_out_of_circle(center: &Point, r: f64, radius: f64) -> Option<Point> {
+    let distance = (center.x * center.x + center.y * center.y).sqrt();
+    if distance >= r + radius {
+        None
+    } else if distance == 0.0 {
+        Some(Point::new(r + radius, 0.0))
+    } else {
+        let depth = r + radius - distance;
+        Some(Point::new(center.x / distance * depth, center.y / distance * depth))
+    }
+}
+
+/// Returns how far a circle must move to leave a convex polygon around the origin
+fn push_out_of_polygon(center: &Point, corners: &[Point], radius: f64) -> Option<Point> {
+    // Find the point of the outline closest to the center of the circle
+    let mut closest = Point::new(0.0, 0.0);
+    let mut closest_distance = f64::INFINITY;
+    for i in 0..corners.len() {
+        let point = center.closest_on_segment(&corners[i], &corners[(i + 1) % corners.len()]);
+        let distance = point.squared_distance_to(center);
+        if distance < closest_distance {
+            closest = point;
+            closest_distance = distance;
+        }
+    }
+
+    let inside = contains(corners, center);
+    let distance = closest_distance.sqrt();
+    if !inside && distance >= radius {
+        return None;
+    }
+
+    // The circle leaves through the closest point of the outline
+    let out = if distance > 0.0 {
+        let sign = if inside { -1.0 } else { 1.0 };
+        Point::new(sign * (center.x - closest.x) / distance, sign * (center.y - closest.y) / distance)
+    } else {
+        let length = (closest.x * closest.x + closest.y * closest.y).sqrt();
+        if length > 0.0 { Point::new(closest.x / length, closest.y / length) } else { Point::new(1.0, 0.0) }
+    };
+    let depth = if inside { distance + radius } else { radius - distance };
+    Some(Point::new(out.x * depth, out.y * depth))
+}
+
+/// Returns true if the point is inside the polygon
+fn contains(corners: &[Point], point: &Point) -> bool {
+    // Count how many edges a ray going right from the point crosses
+    let mut inside = false;
+    let mut j = corners.len().wrapping_sub(1);
+    for i in 0..corners.len() {
+        let (a, b) = (&corners[i], &corners[j]);
+        if (a.y > point.y) != (b.y > point.y) && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::{Point, Size};
+    use super::{Obstacle, Shape};
+
+    /// Circles overlapping each kind of obstacle are pushed out of it, and the
+    /// ones that don't overlap it are left alone
+    #[test]
+    fn push_out() {
+        let circle = Obstacle::new(Point::new(100.0, 100.0), Shape::Circle(50.0), false);
+        let push = circle.push_out(&Point::new(160.0, 100.0), 20.0).unwrap();
+        assert!((push.x - 10.0).abs() < 1e-9 && push.y == 0.0);
+        assert!(circle.push_out(&Point::new(171.0, 100.0), 20.0).is_none());
+
+        let rectangle = Obstacle::new(Point::new(0.0, 0.0), Shape::Rectangle(Size::new(100.0, 40.0)), false);
+        let push = rectangle.push_out(&Point::new(10.0, 15.0), 5.0).unwrap();
+        assert!(push.x == 0.0 && (push.y - 10.0).abs() < 1e-9);
+        assert!(rectangle.push_out(&Point::new(0.0, 30.0), 5.0).is_none());
+
+        let triangle = Obstacle::new(Point::new(0.0, 0.0),
+                                     Shape::Polygon(vec![Point::new(0.0, -50.0), Point::new(50.0, 50.0), Point::new(-50.0, 50.0)]),
+                                     true);
+        let push = triangle.push_out(&Point::new(0.0, 45.0), 10.0).unwrap();
+        assert!(push.x.abs() < 1e-9 && (push.y - 15.0).abs() < 1e-9);
+        assert!(triangle.push_out(&Point::new(0.0, 70.0), 10.0).is_none());
+    }
+}
@@ -1,40 +1,71 @@
-use drawing::color;
+use drawing::color::{self, Color};
 use super::Vector;
 use traits::{Advance, Position};
 
-use graphics::{Context, Ellipse};
-use opengl_graphics::GlGraphics;
+use graphics::Context;
+use renderer::Renderer;
 
 /// A model representing a particle
 ///
 /// Particles are visible objects that have a time to live and move around
-/// in a given direction until their time is up. They are spawned when the
-/// player or an enemy is killed
+/// in a given direction until their time is up. They are spawned by an
+/// `Emitter` when the player moves or when an enemy is killed
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct Particle {
     pub vector: Vector,
-    pub ttl: f64
+    /// The remaining time to live, in seconds
+    pub ttl: f64,
+    /// The total time to live the particle was born with, in seconds
+    pub lifetime: f64,
+    /// The speed at birth, in units per second
+    pub speed: f64,
+    /// The downwards acceleration applied to the particle
+    pub gravity: f64,
+    /// The vertical speed accumulated due to gravity
+    pub fall_speed: f64,
+    /// The colors at birth and at death
+    pub colors: [Color; 2],
+    /// The radius at birth and at death
+    pub sizes: [f64; 2]
 }
 
 derive_position_direction!(Particle);
 
 impl Particle {
-    /// Create a particle with the given vector and time to live in seconds
-    pub fn new(vector: Vector, ttl: f64) -> Particle {
-        Particle { vector: vector, ttl: ttl }
+    /// Returns true if the particle's time is not up yet
+    ///
+    /// When time is rewound, particles go back to before they were born, after
+    /// which they aren't alive either
+    pub fn is_alive(&self) -> bool {
+        self.ttl > 0.0 && self.ttl <= self.lifetime
+    }
+
+    /// Returns the fraction of the lifetime that is left, from 1 (birth) to 0 (death)
+    pub fn life(&self) -> f64 {
+        if self.lifetime > 0.0 { (self.ttl / self.lifetime).max(0.0) } else { 0.0 }
     }
 
     /// Draw the particle
-    pub fn draw(&self, c: &Context, gl: &mut GlGraphics) {
-        let radius = 5.0 * self.ttl;
-        Ellipse::new(color::VIOLET).draw(
-            [self.x() - radius, self.y() - radius, radius * 2.0, radius * 2.0],
-            &c.draw_state, c.transform, gl);
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        let life = self.life();
+        let radius = self.sizes[1] + (self.sizes[0] - self.sizes[1]) * life;
+        let color = color::lerp(self.colors[1], self.colors[0], life as f32);
+        r.ellipse(color, [self.x() - radius, self.y() - radius, radius * 2.0, radius * 2.0], c.transform);
     }
 
     /// Update the particle
+    ///
+    /// A negative `elapsed_time` moves the particle back in time
     pub fn update(&mut self, elapsed_time: f64) {
         self.ttl -= elapsed_time;
-        let speed = 500.0 * self.ttl * self.ttl;
+
+        // The speed decays quadratically as the particle gets older
+        let life = self.life();
+        let speed = self.speed * life * life;
         self.advance(elapsed_time * speed);
+
+        // Gravity pulls the particle down the screen
+        self.fall_speed += self.gravity * elapsed_time;
+        *self.y_mut() += self.fall_speed * elapsed_time;
     }
 }
@@ -0,0 +1,62 @@
+use std::slice;
+
+use super::Particle;
+
+/// A fixed-capacity container for particles
+///
+/// Live particles are kept at the front of the pool. When a particle dies, it
+/// is swapped with the last live one, so its slot can be recycled by the next
+/// particle that is spawned without allocating. Once the pool is full, new
+/// particles are discarded
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct ParticlePool {
+    particles: Vec<Particle>,
+    live: usize,
+    capacity: usize
+}
+
+impl ParticlePool {
+    /// Returns an empty pool that holds at most `capacity` live particles
+    pub fn new(capacity: usize) -> ParticlePool {
+        ParticlePool {
+            particles: Vec::with_capacity(capacity),
+            live: 0,
+            capacity: capacity
+        }
+    }
+
+    /// Adds a particle to the pool, unless it is already full
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.live < self.particles.len() {
+            // Recycle the slot of a dead particle
+            self.particles[self.live] = particle;
+        } else if self.particles.len() < self.capacity {
+            self.particles.push(particle);
+        } else {
+            return;
+        }
+
+        self.live += 1;
+    }
+
+    /// Updates the live particles and retires the ones whose time is up
+    pub fn update(&mut self, elapsed_time: f64) {
+        let mut i = 0;
+        while i < self.live {
+            self.particles[i].update(elapsed_time);
+            if self.particles[i].is_alive() {
+                i += 1;
+            } else {
+                // Move the dead particle out of the live region. The particle
+                // swapped into its place still needs to be updated, so `i` stays
+                self.live -= 1;
+                self.particles.swap(i, self.live);
+            }
+        }
+    }
+
+    /// Returns an iterator over the live particles
+    pub fn iter(&self) -> slice::Iter<Particle> {
+        self.particles[..self.live].iter()
+    }
+}
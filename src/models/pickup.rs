@@ -0,0 +1,45 @@
+use drawing::{color, Point};
+use traits::{Collide, Position};
+
+use graphics::{Context, Transformed};
+use renderer::Renderer;
+
+/// A bonus collected by flying over it, which comes back after a while
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Pickup {
+    pub position: Point,
+    /// The seconds left until the pickup can be collected again
+    pub cooldown: f64
+}
+
+derive_position!(Pickup);
+
+const POLYGON: &'static [[f64; 2]] = &[
+    [0.0, -9.0],
+    [9.0, 0.0],
+    [0.0, 9.0],
+    [-9.0, 0.0]
+];
+
+impl Pickup {
+    /// Create a pickup at the given position, ready to be collected
+    pub fn new(position: Point) -> Pickup {
+        Pickup { position: position, cooldown: 0.0 }
+    }
+
+    /// Returns true if the pickup can be collected
+    pub fn is_available(&self) -> bool {
+        self.cooldown <= 0.0
+    }
+
+    /// Draw the pickup, if it can be collected
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        if self.is_available() {
+            r.polygon(color::CYAN, POLYGON, c.transform.trans(self.x(), self.y()));
+        }
+    }
+}
+
+impl Collide for Pickup {
+    fn radius(&self) -> f64 { 9.0 }
+}
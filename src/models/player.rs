@@ -1,15 +1,42 @@
-use graphics::{Context, Polygon, Transformed};
-use opengl_graphics::GlGraphics;
+use graphics::{Context, Transformed};
 use rand::Rng;
 
 use drawing::{color, Point, Size};
+use drawing::color::Color;
+use renderer::Renderer;
 use super::Vector;
 use traits::{Advance, Collide, Position};
 
-/// The `Player` is the rocket controlled by the user
-#[derive(Default)]
+/// The amount of lives a player starts with
+pub const LIVES: u32 = 3;
+
+/// The amount of seconds a player is invulnerable after respawning
+const RESPAWN_INVULNERABILITY: f64 = 2.0;
+
+/// The fraction of the drift a player loses every second
+const DRIFT_DAMPING: f64 = 1.5;
+
+/// The colors of the players, in order. There can't be more players than colors
+pub const COLORS: [Color; 4] = [color::RED, color::GREEN, color::CYAN, color::VIOLET];
+
+/// The `Player` is a rocket controlled by a user
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct Player {
-    pub vector: Vector
+    pub vector: Vector,
+    /// The color of the rocket
+    pub color: Color,
+    /// The current score of the player
+    pub score: u32,
+    /// The remaining lives. When they reach zero, the player is out of the game
+    pub lives: u32,
+    /// The amount of other players this player has shot down
+    pub kills: u32,
+    /// The amount of times this player has been shot down
+    pub deaths: u32,
+    /// The remaining seconds during which enemies can't hurt the player
+    pub invulnerable: f64,
+    /// The velocity the environment adds to the rocket, in pixels per second
+    pub drift: Point
 }
 
 derive_position_direction!(Player);
@@ -22,19 +49,64 @@ const POLYGON: &'static [[f64; 2]] = &[
 ];
 
 impl Player {
-    /// Create a new `Player` with a random position and direction
-    pub fn random<R: Rng>(rng: &mut R, bounds: Size) -> Player {
-        Player { vector: Vector::random(rng, bounds) }
+    /// Create a new `Player` of the given color with a random position and direction
+    pub fn random<R: Rng>(rng: &mut R, bounds: Size, color: Color) -> Player {
+        Player {
+            vector: Vector::random(rng, bounds),
+            color: color,
+            score: 0,
+            lives: LIVES,
+            kills: 0,
+            deaths: 0,
+            invulnerable: 0.0,
+            drift: Point::new(0.0, 0.0)
+        }
+    }
+
+    /// Returns true if the player has lives left
+    pub fn is_alive(&self) -> bool {
+        self.lives > 0
+    }
+
+    /// Returns true if the player can be hurt
+    pub fn is_vulnerable(&self) -> bool {
+        self.is_alive() && self.invulnerable <= 0.0
+    }
+
+    /// Moves the player to the given position, making it invulnerable for a while
+    pub fn respawn(&mut self, vector: Vector) {
+        self.vector = vector;
+        self.invulnerable = RESPAWN_INVULNERABILITY;
+        self.drift = Point::new(0.0, 0.0);
+    }
+
+    /// Moves the player forward at the given speed, plus the drift caused by the environment
+    ///
+    /// `force` is the acceleration caused by the environment. It builds up the
+    /// drift, which fades away once the force stops
+    pub fn fly(&mut self, dt: f64, speed: f64, force: &Point) {
+        self.advance(dt * speed);
+
+        let damping = (1.0 - DRIFT_DAMPING * dt).max(0.0);
+        self.drift.x = (self.drift.x + force.x * dt) * damping;
+        self.drift.y = (self.drift.y + force.y * dt) * damping;
+        *self.x_mut() += self.drift.x * dt;
+        *self.y_mut() += self.drift.y * dt;
     }
 
     /// Draw the player
-    pub fn draw(&self, c: &Context, gl: &mut GlGraphics) {
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        // While invulnerable, the rocket blinks five times per second
+        if !self.is_alive() || (self.invulnerable * 10.0) as u32 % 2 == 1 {
+            return;
+        }
+
         // Set the center of the player as the origin and rotate it
         let transform = c.transform.trans(self.x(), self.y())
                                    .rot_rad(self.direction());
 
         // Draw a rectangle on the position of the player
-        Polygon::new(color::RED).draw(POLYGON, &c.draw_state, transform, gl);
+        r.polygon(self.color, POLYGON, transform);
     }
 
     /// Returns the nose of the rocket
@@ -0,0 +1,208 @@
+//! Steering behaviours, which make enemies move like a swarm instead of a pile
+//!
+//! Each behaviour returns the direction an enemy would like to move in, as a
+//! vector of length 0 to 1. The kind of the enemy decides how much weight each
+//! behaviour gets, and the enemy turns towards the sum.
+
+use drawing::Point;
+use traits::{Advance, Position};
+use super::{Bullet, Enemy, Obstacle, Player};
+
+/// Enemies closer than this try to move apart, in pixels
+const SEPARATION_RADIUS: f64 = 40.0;
+
+/// Enemies closer than this are part of the same flock, in pixels
+const FLOCK_RADIUS: f64 = 120.0;
+
+/// Bullets closer than this are dodged by enemies that flee, in pixels
+const FLEE_RADIUS: f64 = 90.0;
+
+/// Obstacles closer than this are steered around, in pixels
+const AVOID_RADIUS: f64 = 50.0;
+
+/// How fast players usually fly, used to predict where they are going, in pixels per second
+const PLAYER_SPEED: f64 = 200.0;
+
+/// Enemies don't look further ahead than this when pursuing, in seconds
+const MAX_PREDICTION: f64 = 1.5;
+
+/// How much each behaviour counts for an enemy
+pub struct Weights {
+    /// Moving away from nearby enemies
+    pub separation: f64,
+    /// Flying in the same direction as the flock
+    pub alignment: f64,
+    /// Moving towards the middle of the flock
+    pub cohesion: f64,
+    /// Moving straight towards the nearest player
+    pub seek: f64,
+    /// Moving away from nearby bullets
+    pub flee: f64,
+    /// Moving towards where the nearest player is going to be
+    pub pursue: f64,
+    /// Moving away from nearby obstacles
+    pub avoid: f64
+}
+
+/// Returns the direction the enemy with the given index would like to move in,
+/// or `None` if there are no living players to chase
+pub fn steer(index: usize, enemies: &[Enemy], players: &[Player], bullets: &[Bullet],
+             obstacles: &[Obstacle]) -> Option<Point> {
+    let enemy = &enemies[index];
+    let position = enemy.position();
+    let player = match nearest(&position, players.iter().filter(|p| p.is_alive())) {
+        Some(player) => player,
+        None => return None
+    };
+
+    let weights = enemy.kind.weights();
+    let others: Vec<&Enemy> = enemies.iter().enumerate().filter(|&(i, _)| i != index).map(|(_, e)| e).collect();
+    let behaviours = [
+        (weights.separation, separation(&position, &others)),
+        (weights.alignment, alignment(&position, &others)),
+        (weights.cohesion, cohesion(&position, &others)),
+        (weights.seek, seek(&position, &player.position())),
+        (weights.flee, flee(&position, bullets)),
+        (weights.pursue, pursue(&position, enemy.kind.speed(), player)),
+        (weights.avoid, avoid(&position, obstacles))
+    ];
+
+    let mut force = Point::new(0.0, 0.0);
+    for &(weight, ref direction) in &behaviours {
+        force.x += direction.x * weight;
+        force.y += direction.y * weight;
+    }
+    Some(force)
+}
+
+/// Moves away from the enemies that are too close, more strongly from the closest ones
+fn separation(position: &Point, others: &[&Enemy]) -> Point {
+    let mut force = Point::new(0.0, 0.0);
+    for other in others {
+        let distance = other.position().squared_distance_to(position).sqrt();
+        if distance < SEPARATION_RADIUS {
+            let away = normalize(Point::new(position.x - other.x(), position.y - other.y()));
+            let strength = 1.0 - distance / SEPARATION_RADIUS;
+            force.x += away.x * strength;
+            force.y += away.y * strength;
+        }
+    }
+    limit(force)
+}
+
+/// Flies in the average direction of the flock
+fn alignment(position: &Point, others: &[&Enemy]) -> Point {
+    let flock = flock(position, others);
+    let sum = flock.iter().fold(Point::new(0.0, 0.0), |sum, e| {
+        Point::new(sum.x + e.direction().cos(), sum.y + e.direction().sin())
+    });
+    normalize(sum)
+}
+
+/// Moves towards the middle of the flock
+fn cohesion(position: &Point, others: &[&Enemy]) -> Point {
+    let flock = flock(position, others);
+    if flock.is_empty() {
+        return Point::new(0.0, 0.0);
+    }
+    let n = flock.len() as f64;
+    let sum = flock.iter().fold(Point::new(0.0, 0.0), |sum, e| Point::new(sum.x + e.x(), sum.y + e.y()));
+    seek(position, &Point::new(sum.x / n, sum.y / n))
+}
+
+/// Moves straight towards the target
+fn seek(position: &Point, target: &Point) -> Point {
+    normalize(Point::new(target.x - position.x, target.y - position.y))
+}
+
+/// Moves away from the closest bullet, if any is near
+fn flee(position: &Point, bullets: &[Bullet]) -> Point {
+    match nearest(position, bullets.iter()) {
+        Some(bullet) if bullet.position().squared_distance_to(position) < FLEE_RADIUS * FLEE_RADIUS => {
+            normalize(Point::new(position.x - bullet.x(), position.y - bullet.y()))
+        }
+        _ => Point::new(0.0, 0.0)
+    }
+}
+
+/// Moves towards where the player will be by the time the enemy gets there,
+/// assuming the player keeps flying in the same direction
+fn pursue(position: &Point, speed: f64, player: &Player) -> Point {
+    let time = (player.position().squared_distance_to(position).sqrt() / speed).min(MAX_PREDICTION);
+    let ahead = PLAYER_SPEED * time;
+    seek(position, &Point::new(player.x() + player.direction().cos() * ahead,
+                               player.y() + player.direction().sin() * ahead))
+}
+
+/// Moves away from the obstacles that are too close, more strongly from the closest ones
+fn avoid(position: &Point, obstacles: &[Obstacle]) -> Point {
+    let mut force = Point::new(0.0, 0.0);
+    for obstacle in obstacles {
+        if let Some(push) = obstacle.push_out(position, AVOID_RADIUS) {
+            let away = normalize(push.clone());
+            let strength = ((push.x * push.x + push.y * push.y).sqrt() / AVOID_RADIUS).min(1.0);
+            force.x += away.x * strength;
+            force.y += away.y * strength;
+        }
+    }
+    limit(force)
+}
+
+/// Returns the enemies close enough to be part of the same flock
+fn flock<'a>(position: &Point, others: &[&'a Enemy]) -> Vec<&'a Enemy> {
+    others.iter().cloned().filter(|e| e.position().squared_distance_to(position) < FLOCK_RADIUS * FLOCK_RADIUS).collect()
+}
+
+/// Returns the object closest to the given position
+fn nearest<'a, T: Position + 'a, I: Iterator<Item=&'a T>>(position: &Point, objects: I) -> Option<&'a T> {
+    let mut nearest: Option<(&T, f64)> = None;
+    for object in objects {
+        let distance = object.position().squared_distance_to(position);
+        if nearest.as_ref().map_or(true, |&(_, d)| distance < d) {
+            nearest = Some((object, distance));
+        }
+    }
+    nearest.map(|(object, _)| object)
+}
+
+/// Returns the point scaled to length 1, or the origin if it has no length
+fn normalize(point: Point) -> Point {
+    let length = (point.x * point.x + point.y * point.y).sqrt();
+    if length == 0.0 { point } else { Point::new(point.x / length, point.y / length) }
+}
+
+/// Returns the point scaled down to length 1 if it's longer
+fn limit(point: Point) -> Point {
+    if point.x * point.x + point.y * point.y > 1.0 { normalize(point) } else { point }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::{color, Point, Size};
+    use models::{Enemy, Player, Vector};
+    use models::enemy::Kind;
+    use rng::GameRng;
+    use traits::Position;
+
+    /// Two chasers flying almost on top of each other towards the player must
+    /// move apart instead of merging into one
+    #[test]
+    fn chasers_spread_out() {
+        let mut player = Player::random(&mut GameRng::new(1), Size::new(800.0, 600.0), color::RED);
+        player.vector = Vector::new(Point::new(600.0, 100.0), 0.0);
+        let players = [player];
+        let mut enemies = vec![Enemy::new(Vector::new(Point::new(100.0, 100.0), 0.0), Kind::Chaser),
+                               Enemy::new(Vector::new(Point::new(100.0, 104.0), 0.0), Kind::Chaser)];
+
+        for _ in 0..60 {
+            let steering: Vec<_> = (0..enemies.len()).map(|i| super::steer(i, &enemies, &players, &[], &[])).collect();
+            for (enemy, steering) in enemies.iter_mut().zip(steering) {
+                enemy.update(1.0 / 60.0, steering);
+            }
+        }
+
+        let distance = enemies[0].position().squared_distance_to(&enemies[1].position()).sqrt();
+        assert!(distance > 20.0, "the chasers are {} pixels apart", distance);
+        assert!(enemies[0].x() > 150.0);
+    }
+}
@@ -0,0 +1,33 @@
+use graphics::Context;
+
+use super::Enemy;
+use traits::{Collide, Position};
+use renderer::Renderer;
+
+/// A warning shown where an enemy is about to appear
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Telegraph {
+    /// The enemy that will appear
+    pub enemy: Enemy,
+    /// The seconds left until the enemy appears
+    pub time_left: f64
+}
+
+impl Telegraph {
+    /// Returns a warning for an enemy that appears in the given amount of seconds
+    pub fn new(enemy: Enemy, time_left: f64) -> Telegraph {
+        Telegraph { enemy: enemy, time_left: time_left }
+    }
+
+    /// Draws a faint copy of the enemy, blinking faster as it is about to appear
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        if (self.time_left * 12.0 / (1.0 + self.time_left)) as u32 % 2 == 1 {
+            return;
+        }
+
+        let mut color = self.enemy.kind.color();
+        color[3] = 0.4;
+        let (x, y, radius) = (self.enemy.x(), self.enemy.y(), self.enemy.radius() * 1.5);
+        r.ellipse(color, [x - radius, y - radius, radius * 2.0, radius * 2.0], c.transform);
+    }
+}
@@ -5,7 +5,7 @@ use rand::Rng;
 use drawing::{Point, Size};
 
 /// A `Vector`
-#[derive(Clone, Default)]
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
 pub struct Vector {
     /// The position of the vector
     pub position: Point,
@@ -29,6 +29,29 @@ impl Vector {
         self.direction -= f64::consts::PI;
         self
     }
+
+    /// Linearly interpolates between this vector (`t == 0`) and the given one
+    /// (`t == 1`), rotating the shortest way around
+    ///
+    /// If the vectors are more than half of the bounds apart, the object has
+    /// wrapped around the world in between, so it jumps to the given vector
+    pub fn lerp(&self, to: &Vector, t: f64, bounds: &Size) -> Vector {
+        let (dx, dy) = (to.position.x - self.position.x, to.position.y - self.position.y);
+        if dx.abs() > bounds.width / 2.0 || dy.abs() > bounds.height / 2.0 {
+            return to.clone();
+        }
+
+        let pi = f64::consts::PI;
+        let mut turn = (to.direction - self.direction) % (2.0 * pi);
+        if turn > pi {
+            turn -= 2.0 * pi;
+        } else if turn < -pi {
+            turn += 2.0 * pi;
+        }
+
+        Vector::new(Point::new(self.position.x + dx * t, self.position.y + dy * t),
+                    self.direction + turn * t)
+    }
 }
 
 /// A macro to implement `Position` and `Direction` for any type that has a field named `vector`
@@ -53,3 +76,16 @@ macro_rules! derive_position_direction {
         }
     }
 }
+
+/// A macro to implement `Position` for any type that has a field named `position`
+#[macro_export]
+macro_rules! derive_position {
+    ($t:ty) => {
+        impl ::traits::Position for $t {
+            fn x(&self) -> f64 { self.position.x }
+            fn x_mut(&mut self) -> &mut f64 { &mut self.position.x }
+            fn y(&self) -> f64 { self.position.y }
+            fn y_mut(&mut self) -> &mut f64 { &mut self.position.y }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use drawing::{color, Point};
+use traits::Position;
+
+use graphics::Context;
+use renderer::Renderer;
+
+/// The distance at which the pull of a well equals its strength, in pixels
+const REFERENCE_DISTANCE: f64 = 100.0;
+
+/// Closer than this, the pull of a well stops growing, in pixels
+const MIN_DISTANCE: f64 = 20.0;
+
+/// A gravity well, placed in the world by a level, that pulls the players
+/// and the bullets towards it
+///
+/// Wells with a core are black holes: anything that reaches the core is destroyed
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Well {
+    pub position: Point,
+    /// The pull of the well at 100 pixels from its center, in pixels per second squared.
+    /// It grows with the inverse square of the distance
+    pub strength: f64,
+    /// The radius of the deadly core, or zero for a well without one
+    pub core: f64
+}
+
+derive_position!(Well);
+
+impl Well {
+    /// Create a well of the given strength and core at the given position
+    #[cfg(test)]
+    pub fn new(position: Point, strength: f64, core: f64) -> Well {
+        Well { position: position, strength: strength, core: core }
+    }
+
+    /// Returns the acceleration caused by the well on an object at the given point
+    pub fn pull(&self, point: &Point) -> Point {
+        let (dx, dy) = (self.position.x - point.x, self.position.y - point.y);
+        let distance = (dx * dx + dy * dy).sqrt().max(MIN_DISTANCE);
+        let acceleration = self.strength * (REFERENCE_DISTANCE / distance) * (REFERENCE_DISTANCE / distance);
+        Point::new(dx / distance * acceleration, dy / distance * acceleration)
+    }
+
+    /// Returns true if the given point is inside the core of the well
+    pub fn swallows(&self, point: &Point) -> bool {
+        self.position.squared_distance_to(point) < self.core * self.core
+    }
+
+    /// Draw the well as a faint halo around its center, with the core in black
+    pub fn draw<R: Renderer>(&self, c: &Context, r: &mut R) {
+        let (x, y) = (self.x(), self.y());
+        let halo = self.core + 25.0;
+        r.ellipse([0.6, 0.0, 1.0, 0.25], [x - halo, y - halo, halo * 2.0, halo * 2.0], c.transform);
+        if self.core > 0.0 {
+            r.ellipse(color::BLACK, [x - self.core, y - self.core, self.core * 2.0, self.core * 2.0], c.transform);
+        } else {
+            r.ellipse(color::VIOLET, [x - 4.0, y - 4.0, 8.0, 8.0], c.transform);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::Point;
+    use models::{Bullet, Vector};
+    use traits::Position;
+    use super::Well;
+
+    /// A bullet flying past a well bends towards it, keeping its speed
+    #[test]
+    fn bends_bullets() {
+        let well = Well::new(Point::new(0.0, 0.0), 400.0, 0.0);
+        let mut bullet = Bullet::new(Vector::new(Point::new(-300.0, 100.0), 0.0), 0);
+        let start = bullet.position();
+        for _ in 0..60 {
+            let pull = well.pull(&bullet.position());
+            bullet.update(1.0 / 60.0, &pull);
+        }
+
+        assert!(bullet.y() < 100.0);
+        let travelled = bullet.position().squared_distance_to(&start).sqrt();
+        assert!((travelled - 500.0).abs() < 25.0);
+
+        let pull = well.pull(&Point::new(100.0, 0.0));
+        assert!((pull.x + 400.0).abs() < 1e-9 && pull.y == 0.0);
+    }
+}
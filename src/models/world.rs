@@ -1,45 +1,165 @@
-use graphics;
-use opengl_graphics::GlGraphics;
+use graphics::{self, Transformed};
 use rand::Rng;
 
-use drawing::Size;
-use models::{Bullet, Enemy, Particle, Player};
+use camera::Camera;
+use drawing::{color, Point, Size};
+use models::{Bullet, Enemy, Laser, Obstacle, ParticlePool, Pickup, Player, Telegraph, Well};
+use models::player;
+use renderer::Renderer;
+use traits::Position;
+
+/// The maximum amount of particles alive at the same time
+const MAX_PARTICLES: usize = 2000;
+
+/// The width of the minimap, in pixels. The height depends on the world's aspect ratio
+const MINIMAP_WIDTH: f64 = 160.0;
 
 /// A model that contains the other models and renders them
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct World {
-    pub player: Player,
-    pub particles: Vec<Particle>,
+    pub players: Vec<Player>,
+    pub particles: ParticlePool,
     pub bullets: Vec<Bullet>,
     pub enemies: Vec<Enemy>,
-    pub size: Size
+    /// The warnings of enemies about to appear
+    pub telegraphs: Vec<Telegraph>,
+    /// The layout of the level: obstacles, places where the players appear and pickups
+    pub obstacles: Vec<Obstacle>,
+    pub spawn_points: Vec<Point>,
+    pub pickups: Vec<Pickup>,
+    /// True if the edges of the world are solid walls instead of wrapping around
+    pub walls: bool,
+    /// The hazards of the level: gravity wells, black holes and laser barriers
+    pub wells: Vec<Well>,
+    pub lasers: Vec<Laser>,
+    /// The seconds since the game started, which tell where the lasers are
+    pub time: f64,
+    pub size: Size,
+    pub camera: Camera
 }
 
 impl World {
-    /// Returns a new world of the given size
-    pub fn new<R: Rng>(rng: &mut R, size: Size) -> World {
+    /// Returns a new world of the given size with the given amount of players,
+    /// seen through a screen of size `viewport`
+    pub fn new<R: Rng>(rng: &mut R, size: Size, viewport: Size, players: usize) -> World {
+        let players: Vec<_> = player::COLORS.iter().take(players)
+                                            .map(|&color| Player::random(rng, size.clone(), color))
+                                            .collect();
+        let camera = Camera::new(viewport, players[0].position());
         World {
-            player: Player::random(rng, size.clone()),
-            particles: Vec::with_capacity(1000),
+            players: players,
+            particles: ParticlePool::new(MAX_PARTICLES),
             bullets: vec![],
             enemies: vec![],
-            size: size
+            telegraphs: vec![],
+            obstacles: vec![],
+            spawn_points: vec![],
+            pickups: vec![],
+            walls: false,
+            wells: vec![],
+            lasers: vec![],
+            time: 0.0,
+            size: size,
+            camera: camera
         }
     }
 
-    /// Renders the world and everything in it
-    pub fn render(&self, c: graphics::context::Context, g: &mut GlGraphics) {
-        for particle in &self.particles {
-            particle.draw(&c, g);
+    /// Returns the acceleration the gravity wells cause on an object at the given point
+    pub fn gravity(&self, point: &Point) -> Point {
+        self.wells.iter().map(|w| w.pull(point))
+                  .fold(Point::new(0.0, 0.0), |total, pull| total.translate(&pull))
+    }
+
+    /// Returns true if an object with the given center and radius is destroyed
+    /// by a hazard: the core of a black hole or a laser beam
+    pub fn is_deadly(&self, center: &Point, radius: f64) -> bool {
+        self.wells.iter().any(|w| w.swallows(center))
+        || self.lasers.iter().any(|l| l.hits(self.time, center, radius))
+    }
+
+    /// Renders the world and everything in it, as seen by the camera
+    pub fn render<R: Renderer>(&self, c: graphics::context::Context, r: &mut R) {
+        let c = self.camera.apply(c);
+
+        // Draw the borders of the world, so the player knows where it wraps
+        // (or, if they are walls, where it ends)
+        let border = if self.walls { 4.0 } else { 1.0 };
+        r.rectangle_border(color::GREY, border, [0.0, 0.0, self.size.width, self.size.height], c.transform);
+
+        for obstacle in &self.obstacles {
+            obstacle.draw(&c, r);
+        }
+
+        for pickup in &self.pickups {
+            pickup.draw(&c, r);
+        }
+
+        for well in &self.wells {
+            well.draw(&c, r);
+        }
+
+        for laser in &self.lasers {
+            laser.draw(self.time, &c, r);
+        }
+
+        for particle in self.particles.iter() {
+            particle.draw(&c, r);
         }
 
         for bullet in &self.bullets {
-            bullet.draw(&c, g);
+            bullet.draw(&c, r);
+        }
+
+        for telegraph in &self.telegraphs {
+            telegraph.draw(&c, r);
         }
 
         for enemy in &self.enemies {
-            enemy.draw(&c, g);
+            enemy.draw(&c, r);
+        }
+
+        for player in &self.players {
+            player.draw(&c, r);
         }
+    }
+
+    /// Renders a scaled down version of the world in the top right corner of
+    /// a screen of the given size
+    ///
+    /// The minimap shows the players, every enemy (including the off-screen ones)
+    /// and the region of the world that is currently visible
+    pub fn render_minimap<R: Renderer>(&self, c: graphics::context::Context, r: &mut R, screen: &Size) {
+        let scale = MINIMAP_WIDTH / self.size.width;
+        let margin = 10.0;
+        let c = c.trans(screen.width - MINIMAP_WIDTH - margin, margin);
+        let map = c.transform.scale(scale, scale);
+
+        // Background and border
+        let bounds = [0.0, 0.0, self.size.width, self.size.height];
+        r.rectangle([0.0, 0.0, 0.0, 0.6], bounds, map);
+        r.rectangle_border(color::GREY, 1.0 / scale, bounds, map);
 
-        self.player.draw(&c, g);
+        // The visible region
+        let visible = self.camera.visible_size();
+        r.rectangle_border(color::ORANGE, 1.0 / scale,
+                           [self.camera.center.x - visible.width / 2.0, self.camera.center.y - visible.height / 2.0,
+                            visible.width, visible.height],
+                           map);
+
+        // The obstacles, at their real size
+        for obstacle in &self.obstacles {
+            obstacle.draw(&c.scale(scale, scale), r);
+        }
+
+        // A dot for every enemy and player. They are drawn with a
+        // fixed size on the screen, regardless of the scale
+        let dot = 2.0 / scale;
+        for enemy in &self.enemies {
+            r.ellipse(enemy.kind.color(), [enemy.x() - dot, enemy.y() - dot, dot * 2.0, dot * 2.0], map);
+        }
+
+        for player in self.players.iter().filter(|p| p.is_alive()) {
+            r.ellipse(player.color, [player.x() - dot, player.y() - dot, dot * 2.0, dot * 2.0], map);
+        }
     }
 }
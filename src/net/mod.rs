@@ -0,0 +1,120 @@
+//! Online games between two players over UDP, and a server streaming its
+//! games to spectators
+//!
+//! One player hosts the game and the other one joins it. From then on, both
+//! run the same simulation and only exchange their inputs, relying on the game
+//! being deterministic. See `Session` for the details.
+//!
+//! A server, on the other hand, runs the only copy of its game and sends
+//! snapshots of it to any spectator who asks. See `Server` and `Spectator`.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Like `try!`, but for functions returning an `Option`
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(x) => x, None => return None })
+}
+
+mod protocol;
+mod server;
+mod session;
+mod snapshot;
+mod spectator;
+
+pub use self::server::Server;
+pub use self::session::Session;
+pub use self::spectator::Spectator;
+
+use self::protocol::{Message, MAX_MESSAGE};
+
+/// Returns a socket bound to the given port on every interface. The port 0
+/// picks any free port
+pub fn bind(port: u16) -> Result<UdpSocket, String> {
+    UdpSocket::bind(("0.0.0.0", port)).map_err(|e| format!("Unable to use port {}: {}", port, e))
+}
+
+/// Receives the messages arriving to the socket in the background, so the
+/// game never waits for the network
+fn listen(socket: &UdpSocket) -> Result<Receiver<(Message, SocketAddr)>, String> {
+    try!(socket.set_read_timeout(None).map_err(|e| e.to_string()));
+    let receiving = try!(socket.try_clone().map_err(|e| e.to_string()));
+
+    let (sender, messages) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = vec![0; MAX_MESSAGE];
+        while let Ok((len, from)) = receiving.recv_from(&mut buffer) {
+            if let Some(message) = Message::decode(&buffer[..len]) {
+                // Stop once nobody is listening
+                if sender.send((message, from)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use drawing::Size;
+    use game::{Actions, Game, Mode, ScaleMode};
+    use super::{bind, Session};
+    use super::snapshot::{self, Snapshot};
+
+    /// Plays an online game between two sessions on this machine, without
+    /// input delay so that most inputs arrive late and cause rollbacks
+    #[test]
+    fn loopback() {
+        let socket = bind(0).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let size = Size::new(480.0, 300.0);
+        let resolution = size.clone();
+        let host = thread::spawn(move || Session::host(socket, resolution, Mode::Versus(2), 7, 0, 60).unwrap());
+        let mut guest = Session::join(bind(0).unwrap(), size, ("127.0.0.1", port), 60).unwrap();
+        let mut host = host.join().unwrap();
+
+        for frame in 0..900 {
+            host.set_actions(Actions { rotate_left: frame % 90 < 30, shoot: true, .. Actions::default() });
+            guest.set_actions(Actions { boost: frame % 37 < 20, rotate_right: frame % 50 < 10, .. Actions::default() });
+            host.update();
+            guest.update();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(host.desync(), None);
+        assert_eq!(guest.desync(), None);
+        assert!(host.verified() >= 600, "host verified up to frame {}", host.verified());
+        assert!(guest.verified() >= 600, "guest verified up to frame {}", guest.verified());
+    }
+
+    /// Compresses a snapshot against an older one and checks that it can be restored
+    #[test]
+    fn snapshot_delta() {
+        let mut game = Game::new(Size::new(480.0, 300.0), ScaleMode::Letterbox, Mode::Coop, 3).unwrap();
+        game.set_actions(0, Actions { shoot: true, .. Actions::default() });
+        let mut snapshots = vec![];
+        for frame in 1..181 {
+            game.update(1.0 / 60.0);
+            if frame % 90 == 0 {
+                snapshots.push(Snapshot::of(&game, frame).encode());
+            }
+        }
+
+        let (old, new) = (&snapshots[0], &snapshots[1]);
+        let delta = snapshot::delta(new, old);
+        assert_eq!(snapshot::apply_delta(&delta, old).as_ref(), Some(new));
+        assert_eq!(snapshot::apply_delta(&snapshot::delta(new, &[]), &[]).as_ref(), Some(new));
+        let decoded = Snapshot::decode(new).unwrap();
+        let world = game.world();
+        assert_eq!(decoded.telegraphs.len(), world.telegraphs.len());
+        assert_eq!(decoded.pickups.len(), world.pickups.len());
+        let health: Vec<_> = world.enemies.iter().map(|e| e.health).collect();
+        assert_eq!(decoded.enemies.iter().map(|e| e.health).collect::<Vec<_>>(), health);
+    }
+}
@@ -0,0 +1,216 @@
+//! The messages exchanged by the players of an online game, and by the
+//! server and its spectators
+//!
+//! Every message fits in a single UDP datagram. Numbers are written in
+//! big-endian order, and each set of actions is packed into a single byte.
+
+use std::mem;
+
+use game::{Actions, Mode};
+
+/// The size of the largest message (the largest UDP datagram), in bytes
+pub const MAX_MESSAGE: usize = 65507;
+
+/// The largest amount of inputs sent in a single message
+pub const MAX_INPUTS: usize = 256;
+
+/// A message sent between the host and the player that joined
+pub enum Message {
+    /// Sent by a player who wants to join a hosted game, until the game starts
+    Join,
+    /// The answer of the host to `Join`, with the settings of the game
+    Start { seed: u64, mode: Mode, delay: u32 },
+    /// The inputs of the sender for consecutive frames
+    Inputs {
+        /// The amount of consecutive inputs of the receiver that the sender already has
+        received: u32,
+        /// The frame of the first input
+        start: u32,
+        inputs: Vec<Actions>,
+        /// The frame and the checksum of the last game state the sender confirmed, if any
+        checksum: Option<(u32, u64)>
+    },
+    /// Sent by a spectator to start or keep watching the games of a server
+    Watch {
+        /// The last snapshot received by the spectator, if any
+        ack: Option<u32>,
+        /// True if the spectator already has the level, from `Welcome`
+        welcomed: bool
+    },
+    /// The answer of the server to a spectator without the level, with the
+    /// contents of a level file describing it
+    Welcome { level: String },
+    /// The state of the game of a server, sent to its spectators
+    Snapshot {
+        frame: u32,
+        /// The snapshot the data is relative to, or `None` if it is complete
+        baseline: Option<u32>,
+        /// The snapshot, compressed with `snapshot::delta`
+        data: Vec<u8>
+    }
+}
+
+impl Message {
+    /// Returns the bytes of the message
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        match *self {
+            Message::Join => bytes.push(0),
+            Message::Start { seed, mode, delay } => {
+                bytes.push(1);
+                write_u64(&mut bytes, seed);
+                write_u32(&mut bytes, delay);
+                let name = mode.name();
+                bytes.push(name.len() as u8);
+                bytes.extend(name.bytes());
+            }
+            Message::Inputs { received, start, ref inputs, checksum } => {
+                bytes.push(2);
+                write_u32(&mut bytes, received);
+                write_u32(&mut bytes, start);
+                write_u32(&mut bytes, inputs.len() as u32);
+                bytes.extend(inputs.iter().map(encode_actions));
+                if let Some((frame, checksum)) = checksum {
+                    write_u32(&mut bytes, frame);
+                    write_u64(&mut bytes, checksum);
+                }
+            }
+            // Frames start at 1, so 0 means there is no frame
+            Message::Watch { ack, welcomed } => {
+                bytes.push(3);
+                write_u32(&mut bytes, ack.unwrap_or(0));
+                bytes.push(welcomed as u8);
+            }
+            Message::Welcome { ref level } => {
+                bytes.push(5);
+                bytes.extend(level.bytes());
+            }
+            Message::Snapshot { frame, baseline, ref data } => {
+                bytes.push(4);
+                write_u32(&mut bytes, frame);
+                write_u32(&mut bytes, baseline.unwrap_or(0));
+                bytes.extend(data.iter().cloned());
+            }
+        }
+        bytes
+    }
+
+    /// Returns the message contained in the bytes, or `None` if they don't contain a valid message
+    pub fn decode(bytes: &[u8]) -> Option<Message> {
+        let mut reader = Reader { bytes: bytes };
+        let message = match reader.u8() {
+            Some(0) => Message::Join,
+            Some(1) => {
+                let seed = try_opt!(reader.u64());
+                let delay = try_opt!(reader.u32());
+                let len = try_opt!(reader.u8()) as usize;
+                let name = try_opt!(reader.take(len));
+                let mode = try_opt!(String::from_utf8(name.to_vec()).ok().and_then(|name| Mode::from_name(&name)));
+                Message::Start { seed: seed, mode: mode, delay: delay }
+            }
+            Some(2) => {
+                let received = try_opt!(reader.u32());
+                let start = try_opt!(reader.u32());
+                let len = try_opt!(reader.u32()) as usize;
+                if len > MAX_INPUTS {
+                    return None;
+                }
+                let inputs = try_opt!(reader.take(len)).iter().map(|&b| decode_actions(b)).collect();
+                let checksum = if reader.bytes.is_empty() {
+                    None
+                } else {
+                    Some((try_opt!(reader.u32()), try_opt!(reader.u64())))
+                };
+                Message::Inputs { received: received, start: start, inputs: inputs, checksum: checksum }
+            }
+            Some(3) => {
+                let ack = try_opt!(reader.u32());
+                let welcomed = try_opt!(reader.u8()) != 0;
+                Message::Watch { ack: if ack == 0 { None } else { Some(ack) }, welcomed: welcomed }
+            }
+            Some(4) => {
+                let frame = try_opt!(reader.u32());
+                let baseline = try_opt!(reader.u32());
+                let data = reader.bytes.to_vec();
+                reader.bytes = &[];
+                Message::Snapshot { frame: frame, baseline: if baseline == 0 { None } else { Some(baseline) }, data: data }
+            }
+            Some(5) => {
+                let level = try_opt!(String::from_utf8(reader.bytes.to_vec()).ok());
+                reader.bytes = &[];
+                Message::Welcome { level: level }
+            }
+            _ => return None
+        };
+
+        if reader.bytes.is_empty() { Some(message) } else { None }
+    }
+}
+
+/// Reads values from the front of a slice of bytes
+pub struct Reader<'a> {
+    pub bytes: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    /// Returns the next `len` bytes, if there are enough left
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| b.iter().fold(0, |n, &b| n << 8 | b as u16))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| b.iter().fold(0, |n, &b| n << 8 | b as u32))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|b| b.iter().fold(0, |n, &b| n << 8 | b as u64))
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        self.u32().map(|n| unsafe { mem::transmute(n) })
+    }
+}
+
+pub fn write_u16(bytes: &mut Vec<u8>, n: u16) {
+    bytes.extend((0..2).rev().map(|i| (n >> (i * 8)) as u8));
+}
+
+pub fn write_u32(bytes: &mut Vec<u8>, n: u32) {
+    bytes.extend((0..4).rev().map(|i| (n >> (i * 8)) as u8));
+}
+
+pub fn write_u64(bytes: &mut Vec<u8>, n: u64) {
+    bytes.extend((0..8).rev().map(|i| (n >> (i * 8)) as u8));
+}
+
+pub fn write_f32(bytes: &mut Vec<u8>, x: f32) {
+    write_u32(bytes, unsafe { mem::transmute(x) });
+}
+
+/// Packs the actions into a byte, one bit per action
+fn encode_actions(actions: &Actions) -> u8 {
+    (actions.rotate_left as u8) | (actions.rotate_right as u8) << 1 | (actions.boost as u8) << 2 | (actions.shoot as u8) << 3
+}
+
+/// Unpacks the actions packed by `encode_actions`
+fn decode_actions(byte: u8) -> Actions {
+    Actions {
+        rotate_left: byte & 1 != 0,
+        rotate_right: byte & 2 != 0,
+        boost: byte & 4 != 0,
+        shoot: byte & 8 != 0
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Receiver;
+
+use game::Game;
+use level::Level;
+use super::listen;
+use super::protocol::{Message, MAX_MESSAGE};
+use super::snapshot::{self, Snapshot};
+
+/// How often a snapshot is sent to the spectators, in frames
+const SNAPSHOT_INTERVAL: u32 = 3;
+
+/// The amount of recent snapshots kept to compress the next ones against
+const HISTORY: usize = 32;
+
+/// Spectators that haven't been heard of in this amount of frames are dropped
+const SPECTATOR_TIMEOUT: u32 = 300;
+
+/// A spectator watching the games of the server
+struct Client {
+    address: SocketAddr,
+    /// The last snapshot the spectator received, if any
+    ack: Option<u32>,
+    /// The last frame the spectator was heard of
+    last_heard: u32
+}
+
+/// Streams a game to the spectators that ask for it
+///
+/// The server doesn't run the game itself: it is given the game after each
+/// update. Every few frames, it takes a snapshot of the game and sends it to
+/// each spectator, compressed against the last snapshot that spectator
+/// acknowledged (or on its own, if there is none). The level never changes
+/// during a game, so it isn't part of the snapshots: it is sent to each
+/// spectator that doesn't have it yet.
+pub struct Server {
+    socket: UdpSocket,
+    messages: Receiver<(Message, SocketAddr)>,
+    clients: Vec<Client>,
+    /// The bytes of the last snapshots sent, by frame
+    history: VecDeque<(u32, Vec<u8>)>,
+    /// The amount of updates so far. Frames keep counting when a new game
+    /// starts, so spectators can tell which snapshots are the newest
+    frame: u32
+}
+
+impl Server {
+    /// Returns a server accepting spectators on the given socket
+    pub fn new(socket: UdpSocket) -> Result<Server, String> {
+        let messages = try!(listen(&socket));
+        Ok(Server { socket: socket, messages: messages, clients: vec![], history: VecDeque::new(), frame: 0 })
+    }
+
+    /// Returns the amount of spectators watching the game
+    pub fn spectators(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Handles the messages of the spectators and, if it's time, sends them
+    /// a snapshot of the game. Must be called after every update
+    pub fn update(&mut self, game: &Game) {
+        self.frame += 1;
+        self.receive(game);
+
+        if self.frame % SNAPSHOT_INTERVAL != 0 {
+            return;
+        }
+
+        let bytes = Snapshot::of(game, self.frame).encode();
+        for client in &self.clients {
+            let baseline = client.ack.and_then(|ack| self.history.iter().find(|&&(frame, _)| frame == ack));
+            let message = Message::Snapshot {
+                frame: self.frame,
+                baseline: baseline.map(|&(frame, _)| frame),
+                data: snapshot::delta(&bytes, baseline.map_or(&[][..], |&(_, ref bytes)| &bytes[..]))
+            };
+
+            let message = message.encode();
+            if message.len() <= MAX_MESSAGE {
+                // Lost snapshots are superseded by the next ones, so errors can be ignored
+                let _ = self.socket.send_to(&message, client.address);
+            }
+        }
+
+        self.history.push_back((self.frame, bytes));
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    /// Handles the messages received from the spectators
+    fn receive(&mut self, game: &Game) {
+        while let Ok((message, from)) = self.messages.try_recv() {
+            if let Message::Watch { ack, welcomed } = message {
+                if !welcomed {
+                    let welcome = Message::Welcome { level: Level::from_world(game.world()).to_toml() }.encode();
+                    if welcome.len() <= MAX_MESSAGE {
+                        // The spectator keeps asking until it gets the level
+                        let _ = self.socket.send_to(&welcome, from);
+                    }
+                }
+
+                if !self.clients.iter().any(|c| c.address == from) {
+                    self.clients.push(Client { address: from, ack: None, last_heard: 0 });
+                }
+
+                let client = self.clients.iter_mut().find(|c| c.address == from).unwrap();
+                client.last_heard = self.frame;
+                if ack > client.ack {
+                    client.ack = ack;
+                }
+            }
+        }
+
+        let frame = self.frame;
+        self.clients.retain(|c| frame - c.last_heard < SPECTATOR_TIMEOUT);
+    }
+}
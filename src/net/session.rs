@@ -0,0 +1,326 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use piston::input::Key;
+
+use drawing::Size;
+use game::{Actions, Game, Mode, ScaleMode};
+use super::listen;
+use super::protocol::{Message, MAX_INPUTS, MAX_MESSAGE};
+
+/// The maximum amount of frames the game may run ahead of the last confirmed
+/// one. Beyond that, the game waits for the other player
+const MAX_ROLLBACK: u32 = 12;
+
+/// How often the confirmed state is checked against the other player's, in frames
+const CHECKSUM_INTERVAL: u32 = 60;
+
+/// How many times a `Join` is sent before giving up
+const JOIN_ATTEMPTS: u32 = 40;
+
+/// How long to wait for an answer to a `Join`, in milliseconds
+const JOIN_TIMEOUT: u64 = 250;
+
+/// An online game between two players
+///
+/// Both players run the same deterministic simulation and only exchange their
+/// inputs. The inputs of the local player are applied `delay` frames after they
+/// happen, which gives them time to reach the other player. When the inputs of
+/// the other player are late, the game predicts them (by repeating the last
+/// ones received) and keeps going. Once they arrive, if the prediction was
+/// wrong, the game rolls back to the last confirmed state and simulates the
+/// unconfirmed frames again.
+///
+/// Every now and then the players exchange a checksum of their confirmed state,
+/// to detect when their games got out of sync.
+pub struct Session {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    messages: Receiver<(Message, SocketAddr)>,
+    /// The index of the local player in the game
+    local: usize,
+    seed: u64,
+    mode: Mode,
+    delay: u32,
+    /// The duration of a frame, in seconds
+    dt: f64,
+    /// The game shown to the local player, including the predicted frames
+    game: Game,
+    /// The amount of frames `game` has been updated
+    frame: u32,
+    /// The game up to the last frame for which the inputs of both players are known
+    confirmed: Game,
+    confirmed_frame: u32,
+    /// The actions the local player is currently holding
+    actions: Actions,
+    /// The inputs of the local player for each frame, including the delayed ones
+    local_inputs: Vec<Actions>,
+    /// The inputs received from the other player for each frame
+    remote_inputs: Vec<Actions>,
+    /// The inputs predicted for the other player in the unconfirmed frames
+    predicted: VecDeque<Actions>,
+    /// The amount of local inputs the other player has received
+    peer_received: u32,
+    /// The checksums of the confirmed state that haven't been compared yet
+    checksums: VecDeque<(u32, u64)>,
+    /// The last checksum of the confirmed state, which is sent with every message
+    last_checksum: Option<(u32, u64)>,
+    /// The last checksum received from the other player, until it is compared
+    remote_checksum: Option<(u32, u64)>,
+    /// The last frame whose checksum matched the other player's
+    verified: u32,
+    /// The first frame whose checksum didn't match the other player's, if any
+    desync: Option<u32>
+}
+
+impl Session {
+    /// Waits for a player to join on the given socket and starts a game with
+    /// them, with the given logical resolution
+    ///
+    /// The host is the first player, and decides the settings of the game
+    pub fn host(socket: UdpSocket, resolution: Size, mode: Mode, seed: u64, delay: u32, ups: u64)
+                -> Result<Session, String> {
+        let mut buffer = vec![0; MAX_MESSAGE];
+        loop {
+            let (len, peer) = try!(socket.recv_from(&mut buffer).map_err(|e| format!("Unable to receive: {}", e)));
+            if let Some(Message::Join) = Message::decode(&buffer[..len]) {
+                let session = try!(Session::new(socket, peer, resolution, 0, mode, seed, delay, ups));
+                session.send_start();
+                return Ok(session);
+            }
+        }
+    }
+
+    /// Joins the game hosted at the given address, with the given logical resolution
+    ///
+    /// The player that joins is the second player
+    pub fn join<A: ToSocketAddrs>(socket: UdpSocket, resolution: Size, host: A, ups: u64) -> Result<Session, String> {
+        let host = try!(try!(host.to_socket_addrs().map_err(|e| format!("Invalid address: {}", e)))
+                            .next().ok_or("Invalid address".to_string()));
+        try!(socket.set_read_timeout(Some(Duration::from_millis(JOIN_TIMEOUT))).map_err(|e| e.to_string()));
+
+        let mut buffer = vec![0; MAX_MESSAGE];
+        for _ in 0..JOIN_ATTEMPTS {
+            try!(socket.send_to(&Message::Join.encode(), host).map_err(|e| format!("Unable to send: {}", e)));
+            // Errors are timeouts, after which we try again
+            if let Ok((len, peer)) = socket.recv_from(&mut buffer) {
+                if let Some(Message::Start { seed, mode, delay }) = Message::decode(&buffer[..len]) {
+                    return Session::new(socket, peer, resolution, 1, mode, seed, delay, ups);
+                }
+            }
+        }
+
+        Err(format!("No game hosted at {}", host))
+    }
+
+    fn new(socket: UdpSocket, peer: SocketAddr, resolution: Size, local: usize, mode: Mode, seed: u64, delay: u32,
+           ups: u64) -> Result<Session, String> {
+        let messages = try!(listen(&socket));
+        let mut game = try!(Game::new(resolution, ScaleMode::Letterbox, mode, seed));
+        game.disable_rewind();
+        Ok(Session {
+            socket: socket,
+            peer: peer,
+            messages: messages,
+            local: local,
+            seed: seed,
+            mode: mode,
+            delay: delay,
+            dt: 1.0 / ups as f64,
+            confirmed: game.clone(),
+            game: game,
+            frame: 0,
+            confirmed_frame: 0,
+            actions: Actions::default(),
+            local_inputs: vec![Actions::default(); delay as usize],
+            remote_inputs: vec![Actions::default(); delay as usize],
+            predicted: VecDeque::new(),
+            peer_received: 0,
+            checksums: VecDeque::new(),
+            last_checksum: None,
+            remote_checksum: None,
+            verified: 0,
+            desync: None
+        })
+    }
+
+    /// Returns the game, as seen by the local player
+    pub fn game(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    /// Returns the last frame whose state is known to be the same for both players
+    #[cfg(test)]
+    pub fn verified(&self) -> u32 {
+        self.verified
+    }
+
+    /// Returns the first frame in which the games of the players differed, if they got out of sync
+    #[cfg(test)]
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    /// Processes a key press of the local player, who always uses the keys of the first player
+    pub fn key_press(&mut self, key: Key) {
+        self.actions.handle_key(0, key, true);
+    }
+
+    /// Processes a key release of the local player
+    pub fn key_release(&mut self, key: Key) {
+        self.actions.handle_key(0, key, false);
+    }
+
+    /// Replaces the actions the local player is holding
+    #[cfg(test)]
+    pub fn set_actions(&mut self, actions: Actions) {
+        self.actions = actions;
+    }
+
+    /// Advances the game by a frame, unless it has gone too far ahead of the other player
+    pub fn update(&mut self) {
+        self.receive();
+        self.confirm();
+
+        if self.frame - self.confirmed_frame < MAX_ROLLBACK {
+            self.local_inputs.push(self.actions.clone());
+            let remote = self.predict(self.frame);
+            self.predicted.push_back(remote.clone());
+            let local = self.local_inputs[self.frame as usize].clone();
+            self.step(false, local, remote);
+            self.frame += 1;
+        }
+
+        self.send();
+    }
+
+    /// Handles the messages received from the other player
+    fn receive(&mut self) {
+        while let Ok((message, from)) = self.messages.try_recv() {
+            if from != self.peer {
+                continue;
+            }
+
+            match message {
+                // The answer to the join got lost
+                Message::Join => if self.local == 0 { self.send_start() },
+                Message::Inputs { received, start, inputs, checksum } => {
+                    self.peer_received = cmp::max(self.peer_received, received);
+
+                    // Inputs are sent until they are acknowledged, so they can arrive
+                    // more than once. Only the new ones are kept
+                    let (start, known) = (start as usize, self.remote_inputs.len());
+                    if start <= known {
+                        self.remote_inputs.extend(inputs.into_iter().skip(known - start));
+                    }
+
+                    if checksum.is_some() {
+                        self.remote_checksum = checksum;
+                    }
+                }
+                // The game already started, and the rest are for spectators
+                Message::Start { .. } | Message::Watch { .. } | Message::Welcome { .. } | Message::Snapshot { .. } => {}
+            }
+        }
+    }
+
+    /// Advances the confirmed game as far as the known inputs allow, and rolls
+    /// back the game if any of the predictions turned out to be wrong
+    fn confirm(&mut self) {
+        let known = cmp::min(self.local_inputs.len(), self.remote_inputs.len()) as u32;
+        let mut mispredicted = false;
+        while self.confirmed_frame < cmp::min(known, self.frame) {
+            let frame = self.confirmed_frame as usize;
+            let (local, remote) = (self.local_inputs[frame].clone(), self.remote_inputs[frame].clone());
+            mispredicted |= self.predicted.pop_front().map_or(true, |predicted| predicted != remote);
+            self.step(true, local, remote);
+            self.confirmed_frame += 1;
+
+            if self.confirmed_frame % CHECKSUM_INTERVAL == 0 {
+                let checksum = (self.confirmed_frame, self.confirmed.checksum());
+                self.checksums.push_back(checksum);
+                self.last_checksum = Some(checksum);
+            }
+        }
+
+        // Only the simulation is rolled back: the shown game keeps the size of the window
+        if mispredicted {
+            self.game.restore(self.confirmed.state());
+            for frame in self.confirmed_frame..self.frame {
+                let remote = self.predict(frame);
+                self.predicted[(frame - self.confirmed_frame) as usize] = remote.clone();
+                let local = self.local_inputs[frame as usize].clone();
+                self.step(false, local, remote);
+            }
+        }
+
+        self.compare_checksums();
+    }
+
+    /// Compares the last checksum of the other player against ours for the same frame
+    fn compare_checksums(&mut self) {
+        let (frame, remote) = match self.remote_checksum {
+            Some(checksum) => checksum,
+            None => return
+        };
+
+        // Checksums older than the one received won't be compared anymore
+        while self.checksums.front().map_or(false, |&(f, _)| f < frame) {
+            self.checksums.pop_front();
+        }
+
+        match self.checksums.front().cloned() {
+            Some((f, local)) if f == frame => {
+                self.checksums.pop_front();
+                self.remote_checksum = None;
+                if local == remote {
+                    self.verified = frame;
+                } else if self.desync.is_none() {
+                    println!("The game got out of sync with the other player at frame {}", frame);
+                    self.desync = Some(frame);
+                }
+            }
+            // We haven't confirmed that frame yet
+            _ => {}
+        }
+    }
+
+    /// Returns the inputs of the other player for the given frame, predicting
+    /// them if they haven't arrived yet
+    fn predict(&self, frame: u32) -> Actions {
+        self.remote_inputs.get(frame as usize).or(self.remote_inputs.last()).cloned().unwrap_or(Actions::default())
+    }
+
+    /// Updates the confirmed or the shown game with the given inputs
+    fn step(&mut self, confirmed: bool, local: Actions, remote: Actions) {
+        let game = if confirmed { &mut self.confirmed } else { &mut self.game };
+        game.set_actions(self.local, local);
+        game.set_actions(1 - self.local, remote);
+        game.update(self.dt);
+    }
+
+    /// Sends the local inputs the other player doesn't have yet, together with
+    /// the last checksum of the confirmed state
+    fn send(&self) {
+        let start = self.peer_received as usize;
+        let end = cmp::min(self.local_inputs.len(), start + MAX_INPUTS);
+        let message = Message::Inputs {
+            received: self.remote_inputs.len() as u32,
+            start: start as u32,
+            inputs: self.local_inputs[start..end].to_vec(),
+            checksum: self.last_checksum
+        };
+
+        // Lost messages are sent again in the next frame, so errors can be ignored
+        let _ = self.socket.send_to(&message.encode(), self.peer);
+    }
+
+    /// Sends the settings of the game to the other player
+    fn send_start(&self) {
+        let message = Message::Start { seed: self.seed, mode: self.mode, delay: self.delay };
+        let _ = self.socket.send_to(&message.encode(), self.peer);
+    }
+}
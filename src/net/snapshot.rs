@@ -0,0 +1,221 @@
+use drawing::{Point, Size};
+use game::{Game, Mode};
+use models::{Bullet, Enemy, Pickup, Player, Telegraph, Vector};
+use models::{enemy, player};
+use super::protocol::{write_f32, write_u16, write_u32, Reader};
+
+/// What a spectator needs to know to draw a frame of a game, besides the level
+#[derive(Clone)]
+pub struct Snapshot {
+    /// The frame of the server the snapshot was taken in
+    pub frame: u32,
+    pub mode: Mode,
+    pub size: Size,
+    /// The seconds since the game started, which tell where the lasers are
+    pub time: f64,
+    pub players: Vec<Player>,
+    pub enemies: Vec<Enemy>,
+    pub bullets: Vec<Bullet>,
+    pub telegraphs: Vec<Telegraph>,
+    pub pickups: Vec<Pickup>
+}
+
+impl Snapshot {
+    /// Returns a snapshot of the game
+    pub fn of(game: &Game, frame: u32) -> Snapshot {
+        let world = game.world();
+        Snapshot {
+            frame: frame,
+            mode: game.mode(),
+            size: world.size.clone(),
+            time: world.time,
+            players: world.players.clone(),
+            enemies: world.enemies.clone(),
+            bullets: world.bullets.clone(),
+            telegraphs: world.telegraphs.clone(),
+            pickups: world.pickups.clone()
+        }
+    }
+
+    /// Returns the bytes of the snapshot
+    ///
+    /// Positions and directions are written as 32 bit floats, which are precise
+    /// enough for drawing
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_u32(&mut bytes, self.frame);
+        let name = self.mode.name();
+        bytes.push(name.len() as u8);
+        bytes.extend(name.bytes());
+        write_f32(&mut bytes, self.size.width as f32);
+        write_f32(&mut bytes, self.size.height as f32);
+        write_f32(&mut bytes, self.time as f32);
+        for &len in &[self.players.len(), self.enemies.len(), self.bullets.len(), self.telegraphs.len(),
+                      self.pickups.len()] {
+            write_u16(&mut bytes, len as u16);
+        }
+
+        for player in &self.players {
+            write_vector(&mut bytes, &player.vector);
+            write_f32(&mut bytes, player.invulnerable as f32);
+            for &n in &[player.score, player.lives, player.kills, player.deaths] {
+                write_u32(&mut bytes, n);
+            }
+        }
+        for enemy in &self.enemies {
+            write_vector(&mut bytes, &enemy.vector);
+            bytes.push(enemy.kind as u8);
+            write_u32(&mut bytes, enemy.health);
+        }
+        for bullet in &self.bullets {
+            write_vector(&mut bytes, &bullet.vector);
+            bytes.push(bullet.owner as u8);
+        }
+        for telegraph in &self.telegraphs {
+            write_vector(&mut bytes, &telegraph.enemy.vector);
+            bytes.push(telegraph.enemy.kind as u8);
+            write_f32(&mut bytes, telegraph.time_left as f32);
+        }
+        for pickup in &self.pickups {
+            write_f32(&mut bytes, pickup.position.x as f32);
+            write_f32(&mut bytes, pickup.position.y as f32);
+            write_f32(&mut bytes, pickup.cooldown as f32);
+        }
+        bytes
+    }
+
+    /// Returns the snapshot contained in the bytes, or `None` if they don't contain a valid snapshot
+    pub fn decode(bytes: &[u8]) -> Option<Snapshot> {
+        let mut reader = Reader { bytes: bytes };
+        let frame = try_opt!(reader.u32());
+        let len = try_opt!(reader.u8()) as usize;
+        let name = try_opt!(reader.take(len));
+        let mode = try_opt!(String::from_utf8(name.to_vec()).ok().and_then(|name| Mode::from_name(&name)));
+        let size = Size::new(try_opt!(reader.f32()) as f64, try_opt!(reader.f32()) as f64);
+        let time = try_opt!(reader.f32()) as f64;
+        let counts = (try_opt!(reader.u16()), try_opt!(reader.u16()), try_opt!(reader.u16()), try_opt!(reader.u16()),
+                      try_opt!(reader.u16()));
+
+        let mut snapshot = Snapshot { frame: frame, mode: mode, size: size, time: time, players: vec![], enemies: vec![],
+                                      bullets: vec![], telegraphs: vec![], pickups: vec![] };
+        for i in 0..counts.0 as usize {
+            let vector = try_opt!(read_vector(&mut reader));
+            let invulnerable = try_opt!(reader.f32()) as f64;
+            snapshot.players.push(Player {
+                vector: vector,
+                color: player::COLORS[i % player::COLORS.len()],
+                invulnerable: invulnerable,
+                score: try_opt!(reader.u32()),
+                lives: try_opt!(reader.u32()),
+                kills: try_opt!(reader.u32()),
+                deaths: try_opt!(reader.u32()),
+                drift: Point::new(0.0, 0.0)
+            });
+        }
+        for _ in 0..counts.1 {
+            let vector = try_opt!(read_vector(&mut reader));
+            let kind = *try_opt!(enemy::KINDS.get(try_opt!(reader.u8()) as usize));
+            let mut enemy = Enemy::new(vector, kind);
+            enemy.health = try_opt!(reader.u32());
+            snapshot.enemies.push(enemy);
+        }
+        for _ in 0..counts.2 {
+            let vector = try_opt!(read_vector(&mut reader));
+            snapshot.bullets.push(Bullet::new(vector, try_opt!(reader.u8()) as usize));
+        }
+        for _ in 0..counts.3 {
+            let vector = try_opt!(read_vector(&mut reader));
+            let kind = *try_opt!(enemy::KINDS.get(try_opt!(reader.u8()) as usize));
+            snapshot.telegraphs.push(Telegraph::new(Enemy::new(vector, kind), try_opt!(reader.f32()) as f64));
+        }
+        for _ in 0..counts.4 {
+            let position = Point::new(try_opt!(reader.f32()) as f64, try_opt!(reader.f32()) as f64);
+            let mut pickup = Pickup::new(position);
+            pickup.cooldown = try_opt!(reader.f32()) as f64;
+            snapshot.pickups.push(pickup);
+        }
+
+        if reader.bytes.is_empty() { Some(snapshot) } else { None }
+    }
+
+    /// Returns the state between this snapshot and the next one, where `t == 0`
+    /// is this one and `t == 1` the next one
+    ///
+    /// Entities are matched by their position in the lists. Whatever is not
+    /// interpolated is taken from the next snapshot
+    pub fn interpolate(&self, next: &Snapshot, t: f64) -> Snapshot {
+        let mut snapshot = next.clone();
+        // The time starts over with each game
+        if next.time >= self.time {
+            snapshot.time = self.time + (next.time - self.time) * t;
+        }
+        for (player, from) in snapshot.players.iter_mut().zip(&self.players) {
+            player.vector = from.vector.lerp(&player.vector, t, &self.size);
+        }
+        for (enemy, from) in snapshot.enemies.iter_mut().zip(&self.enemies) {
+            enemy.vector = from.vector.lerp(&enemy.vector, t, &self.size);
+        }
+        for (bullet, from) in snapshot.bullets.iter_mut().zip(&self.bullets) {
+            bullet.vector = from.vector.lerp(&bullet.vector, t, &self.size);
+        }
+        snapshot
+    }
+}
+
+/// Returns the bytes of a snapshot relative to the bytes of a baseline, compressed
+///
+/// The bytes are XORed with the baseline, so whatever didn't change becomes
+/// zeros, and then the runs of zeros are collapsed. The result is a sequence
+/// of runs, each made of the amount of zeros, the amount of other bytes and
+/// the other bytes themselves. An empty baseline compresses the snapshot on
+/// its own
+pub fn delta(bytes: &[u8], baseline: &[u8]) -> Vec<u8> {
+    let xored: Vec<u8> = bytes.iter().enumerate().map(|(i, &b)| b ^ baseline.get(i).cloned().unwrap_or(0)).collect();
+
+    let mut delta = vec![];
+    write_u32(&mut delta, xored.len() as u32);
+    let mut i = 0;
+    while i < xored.len() {
+        let zeros = xored[i..].iter().take(0xFFFF).take_while(|&&b| b == 0).count();
+        i += zeros;
+        let others = xored[i..].iter().take(0xFFFF).take_while(|&&b| b != 0).count();
+        write_u16(&mut delta, zeros as u16);
+        write_u16(&mut delta, others as u16);
+        delta.extend(xored[i..i + others].iter().cloned());
+        i += others;
+    }
+    delta
+}
+
+/// Returns the bytes of the snapshot compressed by `delta` against the same baseline
+pub fn apply_delta(delta: &[u8], baseline: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = Reader { bytes: delta };
+    let len = try_opt!(reader.u32()) as usize;
+
+    let mut bytes = Vec::with_capacity(len);
+    while !reader.bytes.is_empty() {
+        let zeros = try_opt!(reader.u16()) as usize;
+        let others = try_opt!(reader.u16()) as usize;
+        bytes.extend((0..zeros).map(|_| 0));
+        bytes.extend(try_opt!(reader.take(others)).iter().cloned());
+    }
+
+    if bytes.len() != len {
+        return None;
+    }
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b ^= baseline.get(i).cloned().unwrap_or(0);
+    }
+    Some(bytes)
+}
+
+fn write_vector(bytes: &mut Vec<u8>, vector: &Vector) {
+    write_f32(bytes, vector.position.x as f32);
+    write_f32(bytes, vector.position.y as f32);
+    write_f32(bytes, vector.direction as f32);
+}
+
+fn read_vector(reader: &mut Reader) -> Option<Vector> {
+    let (x, y, direction) = (try_opt!(reader.f32()), try_opt!(reader.f32()), try_opt!(reader.f32()));
+    Some(Vector::new(Point::new(x as f64, y as f64), direction as f64))
+}
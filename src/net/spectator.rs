@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::Receiver;
+
+use graphics::{self, Transformed};
+
+use camera::Camera;
+use drawing::{color, Point, Size};
+use level::{self, Level};
+use models::{ParticlePool, World};
+use renderer::Renderer;
+use super::listen;
+use super::protocol::Message;
+use super::snapshot::{self, Snapshot};
+
+/// How far behind the last snapshot the game is shown, in frames. This leaves
+/// room for the next snapshot to arrive, so there is always one to interpolate to
+const INTERPOLATION_DELAY: f64 = 6.0;
+
+/// How often the spectator tells the server it is still watching, in seconds
+const WATCH_INTERVAL: f64 = 0.5;
+
+/// The amount of received snapshots kept to decompress the next ones
+const HISTORY: usize = 32;
+
+/// The amount of decoded snapshots kept to interpolate between them
+const SNAPSHOTS: usize = 8;
+
+/// Watches the game of a server
+///
+/// The spectator doesn't simulate anything: it draws the snapshots sent by
+/// the server on top of the level, which the server sends once. Since the
+/// snapshots only arrive every few frames, the game is shown a bit in the
+/// past, moving the objects smoothly between the two snapshots around the
+/// frame being shown.
+pub struct Spectator {
+    socket: UdpSocket,
+    server: SocketAddr,
+    messages: Receiver<(Message, SocketAddr)>,
+    /// The level of the server, once it has sent it
+    level: Option<Level>,
+    /// The bytes of the last snapshots received, by frame
+    received: VecDeque<(u32, Vec<u8>)>,
+    /// The last snapshots received, oldest first
+    snapshots: VecDeque<Snapshot>,
+    /// The frame of the server being shown, usually between two snapshots
+    time: f64,
+    /// The amount of updates per second of the server
+    ups: f64,
+    /// The seconds since the server was last told we are watching
+    since_watch: f64
+}
+
+impl Spectator {
+    /// Starts watching the server at the given address
+    pub fn new<A: ToSocketAddrs>(socket: UdpSocket, server: A, ups: u64) -> Result<Spectator, String> {
+        let server = try!(try!(server.to_socket_addrs().map_err(|e| format!("Invalid address: {}", e)))
+                              .next().ok_or("Invalid address".to_string()));
+        let messages = try!(listen(&socket));
+        let mut spectator = Spectator {
+            socket: socket,
+            server: server,
+            messages: messages,
+            level: None,
+            received: VecDeque::new(),
+            snapshots: VecDeque::new(),
+            time: 0.0,
+            ups: ups as f64,
+            since_watch: 0.0
+        };
+        spectator.watch();
+        Ok(spectator)
+    }
+
+    /// Handles the level and the snapshots received and advances the time being shown
+    pub fn update(&mut self, dt: f64) {
+        while let Ok((message, from)) = self.messages.try_recv() {
+            if from != self.server {
+                continue;
+            }
+            match message {
+                Message::Welcome { level } => {
+                    if self.level.is_none() {
+                        self.level = level::parse(&level).ok();
+                    }
+                }
+                Message::Snapshot { frame, baseline, data } => self.receive(frame, baseline, &data),
+                _ => {}
+            }
+        }
+
+        // Keep the time being shown a bit behind the last snapshot, catching up
+        // if it falls too far behind (or gets ahead) after a hiccup
+        if let Some(latest) = self.snapshots.back().map(|s| s.frame as f64) {
+            self.time += dt * self.ups;
+            let target = latest - INTERPOLATION_DELAY;
+            if (self.time - target).abs() > INTERPOLATION_DELAY {
+                self.time = target;
+            }
+        }
+
+        self.since_watch += dt;
+        if self.since_watch >= WATCH_INTERVAL {
+            self.watch();
+        }
+    }
+
+    /// Decompresses and keeps a snapshot, unless it is older than the last one
+    fn receive(&mut self, frame: u32, baseline: Option<u32>, data: &[u8]) {
+        if self.snapshots.back().map_or(false, |s| frame <= s.frame) {
+            return;
+        }
+
+        let bytes = match baseline {
+            None => snapshot::apply_delta(data, &[]),
+            Some(baseline) => self.received.iter().find(|&&(f, _)| f == baseline)
+                                  .and_then(|&(_, ref bytes)| snapshot::apply_delta(data, bytes))
+        };
+        // The baseline may be too old to be kept, in which case the server will
+        // send a complete snapshot after our next acknowledgement
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return
+        };
+
+        if let Some(snapshot) = Snapshot::decode(&bytes) {
+            self.snapshots.push_back(snapshot);
+            self.received.push_back((frame, bytes));
+            if self.snapshots.len() > SNAPSHOTS {
+                self.snapshots.pop_front();
+            }
+            if self.received.len() > HISTORY {
+                self.received.pop_front();
+            }
+            self.watch();
+        }
+    }
+
+    /// Tells the server we are watching, acknowledging the last snapshot received
+    fn watch(&mut self) {
+        self.since_watch = 0.0;
+        let message = Message::Watch { ack: self.snapshots.back().map(|s| s.frame), welcomed: self.level.is_some() };
+        let _ = self.socket.send_to(&message.encode(), self.server);
+    }
+
+    /// Returns the state of the game at the time being shown
+    fn current(&self) -> Option<Snapshot> {
+        for (a, b) in self.snapshots.iter().zip(self.snapshots.iter().skip(1)) {
+            if a.frame as f64 <= self.time && self.time < b.frame as f64 {
+                let t = (self.time - a.frame as f64) / (b.frame - a.frame) as f64;
+                return Some(a.interpolate(b, t));
+            }
+        }
+        self.snapshots.back().cloned()
+    }
+
+    /// Renders the whole world, scaled to fit a screen of the given size
+    pub fn render<R: Renderer>(&self, c: graphics::context::Context, r: &mut R, screen: &Size) {
+        r.clear(color::BLACK);
+
+        let (snapshot, level) = match (self.current(), self.level.as_ref()) {
+            (Some(snapshot), Some(level)) => (snapshot, level),
+            _ => {
+                r.text(color::ORANGE, 22, "Waiting for the server...", c.trans(10.0, 20.0).transform);
+                return;
+            }
+        };
+
+        let size = snapshot.size.clone();
+        let mut camera = Camera::new(screen.clone(), Point::new(size.width / 2.0, size.height / 2.0));
+        camera.zoom = (screen.width / size.width).min(screen.height / size.height);
+        let mut world = World {
+            players: snapshot.players,
+            particles: ParticlePool::new(0),
+            bullets: snapshot.bullets,
+            enemies: snapshot.enemies,
+            telegraphs: snapshot.telegraphs,
+            obstacles: vec![],
+            spawn_points: vec![],
+            pickups: vec![],
+            walls: false,
+            wells: vec![],
+            lasers: vec![],
+            time: snapshot.time,
+            size: size,
+            camera: camera
+        };
+        level.apply(&mut world);
+        // The level only tells where the pickups are, not which ones were collected
+        world.pickups = snapshot.pickups;
+        world.render(c, r);
+
+        for (i, player) in world.players.iter().enumerate() {
+            r.text(player.color, 22, &snapshot.mode.summary(i, player), c.trans(10.0, 20.0 + 24.0 * i as f64).transform);
+        }
+    }
+}
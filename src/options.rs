@@ -0,0 +1,212 @@
+//! Command line options
+
+use std::cmp;
+use std::str::FromStr;
+
+use game::{Mode, ScaleMode};
+
+/// The default input delay of online games, in frames
+const DEFAULT_DELAY: u32 = 2;
+
+/// The default maximum length of the games of a batch, in seconds
+const DEFAULT_DURATION: f64 = 300.0;
+
+/// The default amount of threads playing the games of a batch
+const DEFAULT_THREADS: usize = 4;
+
+/// The help message shown when the options are invalid
+pub const USAGE: &'static str = "\
+Usage: rocket [options]
+
+Options:
+    --coop              Play with two players sharing the keyboard
+    --versus <players>  Play rounds of 2 to 4 players shooting each other
+    --expand            Grow the play area with the window instead of letterboxing it
+    --seed <n>          Start the game with the given seed
+    --record <file>     Record the game to a replay file
+    --load <file>       Continue a saved game
+    --level <file>      Play in the level stored in the given file, which is
+                        also where the level editor saves to (default level.toml)
+    --host <port>       Host an online game for two players on the given port
+                        (co-op, unless `--versus 2` is given)
+    --join <address>    Join the online game hosted at the given address,
+                        like 192.168.1.20:7777
+    --delay <frames>    Delay the input of online games by the given amount of
+                        frames, so it arrives in time to the other player (default 2)
+    --server <port>     Run a game flown by the autopilot without a window,
+                        streaming it to the spectators that connect to the
+                        given port (the mode, seed and level options apply)
+    --spectate <address>
+                        Watch the games of the server at the given address
+    --environment       Run games without a window for training agents, driven
+                        by commands on the standard input (see the readme)
+    --batch <games> <file>
+                        Play the given amount of games without a window with the
+                        autopilot, starting from the seed given with `--seed`
+                        (or 0), and save statistics about each player to a CSV
+                        file (or JSON, if the file ends with `.json`)
+    --inputs <replay>   Play the games of a batch with the inputs of a replay
+                        instead of the autopilot
+    --duration <secs>   End the games of a batch after the given amount of
+                        seconds (default 300)
+    --threads <n>       Play the games of a batch in the given amount of
+                        threads (default 4)
+    --screenshot <replay> <frame> <image>
+                        Play a replay without a window and save the given
+                        frame as a PNG image
+    --export-sounds <directory>
+                        Generate the sound effects and the music and save
+                        them as WAV files";
+
+/// The options the game was started with
+pub struct Options {
+    pub mode: Mode,
+    pub scale_mode: ScaleMode,
+    pub seed: Option<u64>,
+    pub record: Option<String>,
+    pub load: Option<String>,
+    pub level: Option<String>,
+    pub host: Option<u16>,
+    pub join: Option<String>,
+    pub delay: u32,
+    pub server: Option<u16>,
+    pub spectate: Option<String>,
+    pub environment: bool,
+    pub batch: Option<Batch>,
+    pub screenshot: Option<Screenshot>,
+    pub export_sounds: Option<String>
+}
+
+/// A request to play many games without a window and save statistics about them
+pub struct Batch {
+    pub games: usize,
+    pub output: String,
+    /// The replay whose inputs are played, instead of the autopilot
+    pub inputs: Option<String>,
+    pub duration: f64,
+    pub threads: usize
+}
+
+/// A request to render a frame of a replay to an image
+pub struct Screenshot {
+    pub replay: String,
+    pub frame: usize,
+    pub image: String
+}
+
+impl Options {
+    /// Parses the given command line arguments (without the name of the program)
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            mode: Mode::Single,
+            scale_mode: ScaleMode::Letterbox,
+            seed: None,
+            record: None,
+            load: None,
+            level: None,
+            host: None,
+            join: None,
+            delay: DEFAULT_DELAY,
+            server: None,
+            spectate: None,
+            environment: false,
+            batch: None,
+            screenshot: None,
+            export_sounds: None
+        };
+
+        let (mut inputs, mut duration, mut threads): (Option<String>, Option<f64>, Option<usize>) = (None, None, None);
+        while let Some(arg) = args.next() {
+            match &arg[..] {
+                "--coop" => options.mode = Mode::Coop,
+                "--versus" => {
+                    let players = try!(number(&mut args, &arg));
+                    options.mode = try!(Mode::versus(players).ok_or(format!("Invalid value for `{}`: {}", arg, players)));
+                }
+                "--expand" => options.scale_mode = ScaleMode::Expand,
+                "--seed" => options.seed = Some(try!(number(&mut args, &arg))),
+                "--record" => options.record = Some(try!(value(&mut args, &arg))),
+                "--load" => options.load = Some(try!(value(&mut args, &arg))),
+                "--level" => options.level = Some(try!(value(&mut args, &arg))),
+                "--host" => options.host = Some(try!(number(&mut args, &arg))),
+                "--join" => options.join = Some(try!(value(&mut args, &arg))),
+                "--delay" => options.delay = try!(number(&mut args, &arg)),
+                "--server" => options.server = Some(try!(number(&mut args, &arg))),
+                "--spectate" => options.spectate = Some(try!(value(&mut args, &arg))),
+                "--environment" => options.environment = true,
+                "--batch" => {
+                    options.batch = Some(Batch {
+                        games: try!(number(&mut args, &arg)),
+                        output: try!(value(&mut args, &arg)),
+                        inputs: None,
+                        duration: DEFAULT_DURATION,
+                        threads: DEFAULT_THREADS
+                    });
+                }
+                "--inputs" => inputs = Some(try!(value(&mut args, &arg))),
+                "--duration" => duration = Some(try!(number(&mut args, &arg))),
+                "--threads" => threads = Some(try!(number(&mut args, &arg))),
+                "--screenshot" => {
+                    options.screenshot = Some(Screenshot {
+                        replay: try!(value(&mut args, &arg)),
+                        frame: try!(number(&mut args, &arg)),
+                        image: try!(value(&mut args, &arg))
+                    });
+                }
+                "--export-sounds" => options.export_sounds = Some(try!(value(&mut args, &arg))),
+                _ => return Err(format!("Unknown option `{}`", arg))
+            }
+        }
+
+        match options.batch {
+            Some(ref mut batch) => {
+                batch.inputs = inputs;
+                batch.duration = duration.unwrap_or(batch.duration);
+                batch.threads = cmp::max(threads.unwrap_or(batch.threads), 1);
+            }
+            None if inputs.is_some() || duration.is_some() || threads.is_some() => {
+                return Err("`--inputs`, `--duration` and `--threads` can only be used with `--batch`".to_string());
+            }
+            None => {}
+        }
+
+        let online = options.host.is_some() || options.join.is_some();
+        if options.host.is_some() && options.join.is_some() {
+            return Err("`--host` and `--join` can't be used together".to_string());
+        }
+        if online && options.record.is_some() {
+            return Err("Online games can't be recorded".to_string());
+        }
+        if online && options.level.is_some() {
+            return Err("Online games can't be played in levels".to_string());
+        }
+        // Replays are played back letterboxed, while the play area of `--expand`
+        // depends on the window
+        if options.record.is_some() && options.scale_mode == ScaleMode::Expand {
+            return Err("Games played with `--expand` can't be recorded".to_string());
+        }
+        if options.load.is_some() && (online || options.record.is_some()) {
+            return Err("Saved games can't be played online or recorded".to_string());
+        }
+        // Online games are co-op unless another two player mode is chosen
+        if online && options.mode == Mode::Single {
+            options.mode = Mode::Coop;
+        }
+        if online && options.mode.players() != 2 {
+            return Err("Online games are for two players".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the next argument, which is a value of the given flag
+fn value<I: Iterator<Item=String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or(format!("Missing value for `{}`", flag))
+}
+
+/// Returns the next argument, which is a numeric value of the given flag
+fn number<T: FromStr, I: Iterator<Item=String>>(args: &mut I, flag: &str) -> Result<T, String> {
+    let value = try!(value(args, flag));
+    value.parse().map_err(|_| format!("Invalid value for `{}`: {}", flag, value))
+}
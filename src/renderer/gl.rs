@@ -0,0 +1,47 @@
+use graphics::{self, DrawState, Ellipse, Polygon, Rectangle, Text};
+use graphics::math::Matrix2d;
+use opengl_graphics::GlGraphics;
+use opengl_graphics::glyph_cache::GlyphCache;
+
+use drawing::color::Color;
+use super::Renderer;
+
+/// A `Renderer` that draws on the screen using OpenGL
+pub struct GlRenderer<'a> {
+    gl: &'a mut GlGraphics,
+    font: &'a mut GlyphCache<'static>,
+    draw_state: DrawState
+}
+
+impl<'a> GlRenderer<'a> {
+    /// Returns a renderer that draws on `gl`, writing text with the given font
+    pub fn new(gl: &'a mut GlGraphics, font: &'a mut GlyphCache<'static>, draw_state: DrawState) -> GlRenderer<'a> {
+        GlRenderer { gl: gl, font: font, draw_state: draw_state }
+    }
+}
+
+impl<'a> Renderer for GlRenderer<'a> {
+    fn clear(&mut self, color: Color) {
+        graphics::clear(color, self.gl);
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        Ellipse::new(color).draw(rect, &self.draw_state, transform, self.gl);
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        Rectangle::new(color).draw(rect, &self.draw_state, transform, self.gl);
+    }
+
+    fn rectangle_border(&mut self, color: Color, radius: f64, rect: [f64; 4], transform: Matrix2d) {
+        Rectangle::new_border(color, radius).draw(rect, &self.draw_state, transform, self.gl);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Matrix2d) {
+        Polygon::new(color).draw(points, &self.draw_state, transform, self.gl);
+    }
+
+    fn text(&mut self, color: Color, size: u32, text: &str, transform: Matrix2d) {
+        Text::colored(color, size).draw(text, self.font, &self.draw_state, transform, self.gl);
+    }
+}
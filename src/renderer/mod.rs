@@ -0,0 +1,43 @@
+//! An abstraction over the graphics backend
+//!
+//! The models draw themselves through the `Renderer` trait, so they don't
+//! depend on OpenGL. Transforms are computed with the math from the `graphics`
+//! crate (see `graphics::Transformed`), which doesn't need a GPU either
+
+mod gl;
+#[cfg(test)]
+mod recorder;
+mod software;
+
+pub use self::gl::GlRenderer;
+#[cfg(test)]
+pub use self::recorder::{DrawCommand, Recorder};
+pub use self::software::Canvas;
+
+use graphics::math::Matrix2d;
+
+use drawing::color::Color;
+
+/// A renderer draws basic shapes and text on a surface
+///
+/// Every method receives a transform, which maps the given coordinates to
+/// the coordinates of the surface
+pub trait Renderer {
+    /// Fills the whole surface with the given color
+    fn clear(&mut self, color: Color);
+
+    /// Draws a filled ellipse inscribed in the given rectangle (x, y, width, height)
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d);
+
+    /// Draws a filled rectangle (x, y, width, height)
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d);
+
+    /// Draws the border of a rectangle (x, y, width, height), with lines of the given radius
+    fn rectangle_border(&mut self, color: Color, radius: f64, rect: [f64; 4], transform: Matrix2d);
+
+    /// Draws a filled polygon
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Matrix2d);
+
+    /// Draws a line of text with the given font size, starting at the baseline
+    fn text(&mut self, color: Color, size: u32, text: &str, transform: Matrix2d);
+}
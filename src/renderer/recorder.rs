@@ -0,0 +1,90 @@
+use graphics::math::Matrix2d;
+
+use drawing::color::Color;
+use super::Renderer;
+
+/// A draw call, as received by a `Recorder`
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Clear(Color),
+    Ellipse(Color, [f64; 4], Matrix2d),
+    Rectangle(Color, [f64; 4], Matrix2d),
+    RectangleBorder(Color, f64, [f64; 4], Matrix2d),
+    Polygon(Color, Vec<[f64; 2]>, Matrix2d),
+    Text(Color, u32, String, Matrix2d)
+}
+
+/// A `Renderer` that doesn't draw anything, but records the draw calls it receives
+///
+/// This makes it possible to check exactly what was drawn in a frame without
+/// a window or a GPU
+#[derive(Default)]
+pub struct Recorder {
+    pub commands: Vec<DrawCommand>
+}
+
+impl Recorder {
+    /// Returns a recorder without any commands
+    pub fn new() -> Recorder {
+        Recorder { commands: vec![] }
+    }
+
+    /// Returns the recorded commands, leaving the recorder empty for the next frame
+    pub fn take(&mut self) -> Vec<DrawCommand> {
+        ::std::mem::replace(&mut self.commands, vec![])
+    }
+}
+
+impl Renderer for Recorder {
+    fn clear(&mut self, color: Color) {
+        self.commands.push(DrawCommand::Clear(color));
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        self.commands.push(DrawCommand::Ellipse(color, rect, transform));
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        self.commands.push(DrawCommand::Rectangle(color, rect, transform));
+    }
+
+    fn rectangle_border(&mut self, color: Color, radius: f64, rect: [f64; 4], transform: Matrix2d) {
+        self.commands.push(DrawCommand::RectangleBorder(color, radius, rect, transform));
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Matrix2d) {
+        self.commands.push(DrawCommand::Polygon(color, points.to_vec(), transform));
+    }
+
+    fn text(&mut self, color: Color, size: u32, text: &str, transform: Matrix2d) {
+        self.commands.push(DrawCommand::Text(color, size, text.to_string(), transform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graphics::Context;
+
+    use drawing::{color, Size};
+    use game::{Game, Mode, ScaleMode};
+    use super::{DrawCommand, Recorder};
+
+    /// Records a frame of a new game and checks that the screen is cleared
+    /// first and that the HUD shows the score of the player
+    #[test]
+    fn records_frame() {
+        let mut game = Game::new(Size::new(800.0, 600.0), ScaleMode::Letterbox, Mode::Single, 0).unwrap();
+        let mut recorder = Recorder::new();
+        game.render(Context::abs(800.0, 600.0), &mut recorder);
+
+        let commands = recorder.take();
+        assert!(recorder.commands.is_empty());
+        assert_eq!(commands[0], DrawCommand::Clear(color::BLACK));
+
+        let summary = Mode::Single.summary(0, &game.world().players[0]);
+        assert!(commands.iter().any(|command| match *command {
+            DrawCommand::Text(c, 22, ref text, _) => c == color::ORANGE && *text == summary,
+            _ => false
+        }));
+    }
+}
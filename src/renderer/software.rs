@@ -0,0 +1,258 @@
+use std::f64;
+use std::path::Path;
+
+use freetype;
+use graphics::math::Matrix2d;
+use image;
+
+use drawing::color::Color;
+use super::Renderer;
+
+/// The positions inside a pixel that are sampled to compute its coverage
+const SAMPLES: [(f64, f64); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+
+/// A `Renderer` that draws on an image in memory, without a GPU
+///
+/// Shapes are antialiased by sampling each pixel at several positions. The
+/// transforms received are expected to map to normalized device coordinates,
+/// like the ones from `graphics::Context::abs`
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    /// The pixels in RGBA format, row by row
+    pixels: Vec<u8>,
+    /// The font used to draw text. Without a font, text is not drawn
+    font: Option<freetype::Face<'static>>
+}
+
+impl Canvas {
+    /// Returns a black canvas of the given size
+    pub fn new(width: u32, height: u32) -> Canvas {
+        let pixels = [0, 0, 0, 255].iter().cloned().cycle().take((width * height * 4) as usize).collect();
+        Canvas { width: width, height: height, pixels: pixels, font: None }
+    }
+
+    /// Returns a black canvas of the given size that draws text with the given font
+    pub fn with_font<P: AsRef<Path>>(width: u32, height: u32, font: P) -> Result<Canvas, String> {
+        let library = try!(freetype::Library::init().map_err(|e| format!("{:?}", e)));
+        let face = try!(library.new_face(font.as_ref(), 0).map_err(|e| format!("{:?}", e)));
+        let mut canvas = Canvas::new(width, height);
+        canvas.font = Some(face);
+        Ok(canvas)
+    }
+
+    /// Returns the width of the canvas in pixels
+    #[cfg(test)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the canvas in pixels
+    #[cfg(test)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the pixels in RGBA format, row by row
+    #[cfg(test)]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Saves the canvas as a PNG image
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        image::save_buffer(path, &self.pixels, self.width, self.height, image::ColorType::RGBA(8))
+            .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+
+    /// Returns a transform that maps the coordinates given to the renderer to pixels
+    fn to_pixels(&self, transform: Matrix2d) -> Matrix2d {
+        let (w, h) = (self.width as f64 / 2.0, self.height as f64 / 2.0);
+        multiply([[w, 0.0, w], [0.0, -h, h]], transform)
+    }
+
+    /// Blends the color into the pixel, weighted by the given coverage (between 0 and 1)
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+
+        let alpha = color[3] as f64 * coverage;
+        let index = ((y as u32 * self.width + x as u32) * 4) as usize;
+        for channel in 0..3 {
+            let dst = self.pixels[index + channel] as f64;
+            let src = color[channel] as f64 * 255.0;
+            self.pixels[index + channel] = (src * alpha + dst * (1.0 - alpha)).round() as u8;
+        }
+    }
+
+    /// Fills the pixels inside `bounds` (min x, min y, max x, max y, in pixels)
+    /// for which `inside` returns true
+    fn fill<F: Fn(f64, f64) -> bool>(&mut self, color: Color, bounds: [f64; 4], inside: F) {
+        let min_x = bounds[0].floor().max(0.0) as i64;
+        let min_y = bounds[1].floor().max(0.0) as i64;
+        let max_x = bounds[2].ceil().min(self.width as f64) as i64;
+        let max_y = bounds[3].ceil().min(self.height as f64) as i64;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let hits = SAMPLES.iter()
+                                  .filter(|&&(sx, sy)| inside(x as f64 + sx, y as f64 + sy))
+                                  .count();
+                if hits > 0 {
+                    self.blend(x, y, color, hits as f64 / SAMPLES.len() as f64);
+                }
+            }
+        }
+    }
+
+    /// Fills the region of the rectangle (in local coordinates) for which `inside`
+    /// returns true. `inside` receives coordinates relative to the rectangle,
+    /// between 0 and 1
+    fn fill_local<F: Fn(f64, f64) -> bool>(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d, inside: F) {
+        let m = self.to_pixels(transform);
+        let inverse = match invert(m) {
+            Some(inverse) => inverse,
+            None => return
+        };
+
+        let corners = [apply(m, rect[0], rect[1]), apply(m, rect[0] + rect[2], rect[1]),
+                       apply(m, rect[0], rect[1] + rect[3]), apply(m, rect[0] + rect[2], rect[1] + rect[3])];
+        let bounds = bounding_box(&corners);
+
+        self.fill(color, bounds, |x, y| {
+            let (lx, ly) = apply(inverse, x, y);
+            let (u, v) = ((lx - rect[0]) / rect[2], (ly - rect[1]) / rect[3]);
+            0.0 <= u && u <= 1.0 && 0.0 <= v && v <= 1.0 && inside(u, v)
+        });
+    }
+}
+
+impl Renderer for Canvas {
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_mut(4) {
+            for channel in 0..4 {
+                pixel[channel] = (color[channel] * 255.0).round() as u8;
+            }
+        }
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        self.fill_local(color, rect, transform, |u, v| {
+            let (dx, dy) = (u - 0.5, v - 0.5);
+            dx * dx + dy * dy <= 0.25
+        });
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Matrix2d) {
+        self.fill_local(color, rect, transform, |_, _| true);
+    }
+
+    fn rectangle_border(&mut self, color: Color, radius: f64, rect: [f64; 4], transform: Matrix2d) {
+        // Like in `graphics`, the border is centered on the edges of the rectangle
+        let (x, y, w, h, d) = (rect[0], rect[1], rect[2], rect[3], radius * 2.0);
+        self.rectangle(color, [x - radius, y - radius, w + d, d], transform);
+        self.rectangle(color, [x - radius, y + h - radius, w + d, d], transform);
+        self.rectangle(color, [x - radius, y + radius, d, h - d], transform);
+        self.rectangle(color, [x + w - radius, y + radius, d, h - d], transform);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Matrix2d) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let m = self.to_pixels(transform);
+        let points: Vec<(f64, f64)> = points.iter().map(|p| apply(m, p[0], p[1])).collect();
+        let bounds = bounding_box(&points);
+
+        // Even-odd rule: a point is inside if a ray from it crosses the edges an odd amount of times
+        self.fill(color, bounds, |x, y| {
+            let mut inside = false;
+            let mut j = points.len() - 1;
+            for i in 0..points.len() {
+                let ((xi, yi), (xj, yj)) = (points[i], points[j]);
+                if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                    inside = !inside;
+                }
+                j = i;
+            }
+            inside
+        });
+    }
+
+    fn text(&mut self, color: Color, size: u32, text: &str, transform: Matrix2d) {
+        let m = self.to_pixels(transform);
+
+        // Only the translation and the scale of the transform are taken into account
+        let scale = (m[0][0] * m[1][1] - m[0][1] * m[1][0]).abs().sqrt();
+        let (mut pen_x, pen_y) = apply(m, 0.0, 0.0);
+
+        // Render the glyphs first, since the face can't be borrowed while blending
+        let mut glyphs = vec![];
+        if let Some(ref face) = self.font {
+            if face.set_pixel_sizes(0, (size as f64 * scale).round() as u32).is_err() {
+                return;
+            }
+
+            for ch in text.chars() {
+                if face.load_char(ch as usize, freetype::face::RENDER).is_err() {
+                    continue;
+                }
+
+                let glyph = face.glyph();
+                let bitmap = glyph.bitmap();
+                glyphs.push((pen_x + glyph.bitmap_left() as f64,
+                             pen_y - glyph.bitmap_top() as f64,
+                             bitmap.width(), bitmap.rows(), bitmap.pitch(),
+                             bitmap.buffer().to_vec()));
+                pen_x += (glyph.advance().x >> 6) as f64;
+            }
+        }
+
+        for (left, top, width, rows, pitch, buffer) in glyphs {
+            for row in 0..rows {
+                for column in 0..width {
+                    let coverage = buffer[(row * pitch + column) as usize] as f64 / 255.0;
+                    if coverage > 0.0 {
+                        self.blend(left.round() as i64 + column as i64, top.round() as i64 + row as i64,
+                                   color, coverage);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the composition of two affine transforms (`b` is applied first)
+fn multiply(a: Matrix2d, b: Matrix2d) -> Matrix2d {
+    [[a[0][0] * b[0][0] + a[0][1] * b[1][0],
+      a[0][0] * b[0][1] + a[0][1] * b[1][1],
+      a[0][0] * b[0][2] + a[0][1] * b[1][2] + a[0][2]],
+     [a[1][0] * b[0][0] + a[1][1] * b[1][0],
+      a[1][0] * b[0][1] + a[1][1] * b[1][1],
+      a[1][0] * b[0][2] + a[1][1] * b[1][2] + a[1][2]]]
+}
+
+/// Returns the inverse of an affine transform, if it has one
+fn invert(m: Matrix2d) -> Option<Matrix2d> {
+    let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    if det == 0.0 {
+        return None;
+    }
+
+    Some([[m[1][1] / det, -m[0][1] / det, (m[0][1] * m[1][2] - m[1][1] * m[0][2]) / det],
+          [-m[1][0] / det, m[0][0] / det, (m[1][0] * m[0][2] - m[0][0] * m[1][2]) / det]])
+}
+
+/// Applies an affine transform to a point
+fn apply(m: Matrix2d, x: f64, y: f64) -> (f64, f64) {
+    (m[0][0] * x + m[0][1] * y + m[0][2], m[1][0] * x + m[1][1] * y + m[1][2])
+}
+
+/// Returns the smallest box (min x, min y, max x, max y) containing the points
+fn bounding_box(points: &[(f64, f64)]) -> [f64; 4] {
+    points.iter().fold([f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY],
+                       |b, &(x, y)| [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)])
+}
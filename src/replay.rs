@@ -0,0 +1,226 @@
+//! Recording and playback of games
+//!
+//! Since the game is deterministic, a replay only needs the seed and the mode
+//! of the game and the actions that were active during each update.
+//!
+//! Replays are stored as text files. The header contains the seed, the amount
+//! of updates per second, the mode and, if the game was played in a level,
+//! the whole level (each line of the level file, prefixed by `>`), so the
+//! replay still plays back after the level file changes. It is followed by
+//! one line per run of identical actions: the amount of updates in the run and the actions of each
+//! player, written as `L` (rotate left), `R` (rotate right), `B` (boost) and
+//! `S` (shoot), or `-` when inactive. For instance, in a two players game:
+//!
+//! ```text
+//! rocket-replay 3
+//! seed 42
+//! ups 60
+//! mode coop
+//! 120 ---- ----
+//! 30 L--S --B-
+//! ```
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use game::{Actions, Game, Mode};
+use level::{self, Level};
+
+/// The version of the file format, written in the header
+const VERSION: u32 = 3;
+
+/// A recording of a game
+pub struct Replay {
+    /// The seed the game was created with
+    pub seed: u64,
+    /// The amount of updates per second
+    pub ups: u64,
+    /// The mode of the game
+    pub mode: Mode,
+    /// The level the game was played in, if any
+    pub level: Option<Level>,
+    /// The actions of each player active during each update
+    pub inputs: Vec<Vec<Actions>>
+}
+
+impl Replay {
+    /// Returns an empty replay for a game with the given seed and mode
+    pub fn new(seed: u64, ups: u64, mode: Mode) -> Replay {
+        Replay { seed: seed, ups: ups, mode: mode, level: None, inputs: vec![] }
+    }
+
+    /// Records the actions of every player for the next update
+    pub fn record(&mut self, actions: &[Actions]) {
+        self.inputs.push(actions.to_vec());
+    }
+
+    /// Feeds the first `updates` recorded actions to the game, updating it after each
+    ///
+    /// If the replay is shorter than requested, the game keeps running without input
+    pub fn play(&self, game: &mut Game, updates: usize) {
+        let dt = 1.0 / self.ups as f64;
+        for i in 0..updates {
+            self.apply(game, i);
+            game.update(dt);
+        }
+    }
+
+    /// Sets the actions of every player to the ones recorded for the given update
+    pub fn apply(&self, game: &mut Game, update: usize) {
+        for player in 0..self.mode.players() {
+            let actions = self.inputs.get(update).and_then(|inputs| inputs.get(player)).cloned();
+            game.set_actions(player, actions.unwrap_or(Actions::default()));
+        }
+    }
+
+    /// Loads a replay from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, String> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+                 .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+
+        let mut lines = source.lines();
+        let error = |line: &str| format!("Invalid replay {}: unexpected `{}`", path.display(), line);
+
+        let header = lines.next().unwrap_or("");
+        if header != format!("rocket-replay {}", VERSION) {
+            return Err(error(header));
+        }
+
+        let mut replay = Replay::new(0, 0, Mode::Single);
+        let mut level_source: Option<String> = None;
+        for line in lines {
+            if line.starts_with('>') {
+                let source = try!(level_source.as_mut().ok_or(error(line)));
+                if line.starts_with("> ") {
+                    source.push_str(&line[2..]);
+                } else if line != ">" {
+                    return Err(error(line));
+                }
+                source.push('\n');
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("seed"), Some(seed)) => replay.seed = try!(seed.parse().map_err(|_| error(line))),
+                (Some("ups"), Some(ups)) => replay.ups = try!(ups.parse().map_err(|_| error(line))),
+                (Some("mode"), Some(mode)) => replay.mode = try!(Mode::from_name(mode).ok_or(error(line))),
+                (Some("level"), None) => level_source = Some(String::new()),
+                (Some(count), Some(first)) => {
+                    let count: usize = try!(count.parse().map_err(|_| error(line)));
+                    let mut actions = vec![];
+                    for word in Some(first).into_iter().chain(words) {
+                        actions.push(try!(decode_actions(word).ok_or(error(line))));
+                    }
+                    if actions.len() != replay.mode.players() {
+                        return Err(error(line));
+                    }
+                    for _ in 0..count {
+                        replay.inputs.push(actions.clone());
+                    }
+                }
+                (None, None) => (),
+                _ => return Err(error(line))
+            }
+        }
+
+        if replay.ups == 0 {
+            return Err(format!("Invalid replay {}: missing updates per second", path.display()));
+        }
+        if let Some(source) = level_source {
+            replay.level = Some(try!(level::parse(&source).map_err(|e| format!("Invalid replay {}: {}", path.display(), e))));
+        }
+
+        Ok(replay)
+    }
+
+    /// Saves the replay to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let mut contents = format!("rocket-replay {}\nseed {}\nups {}\nmode {}\n",
+                                   VERSION, self.seed, self.ups, self.mode.name());
+        if let Some(ref level) = self.level {
+            contents.push_str("level\n");
+            for line in level.to_toml().lines() {
+                contents.push_str(format!("> {}", line).trim_right());
+                contents.push('\n');
+            }
+        }
+
+        // Collapse runs of identical actions into a single line
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let run = self.inputs[i..].iter().take_while(|a| **a == self.inputs[i]).count();
+            let actions: Vec<_> = self.inputs[i].iter().map(encode_actions).collect();
+            contents.push_str(&format!("{} {}\n", run, actions.join(" ")));
+            i += run;
+        }
+
+        let path = path.as_ref();
+        File::create(path).and_then(|mut f| f.write_all(contents.as_bytes()))
+                          .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+}
+
+/// Returns the textual representation of the actions
+fn encode_actions(actions: &Actions) -> String {
+    let flags = [(actions.rotate_left, 'L'), (actions.rotate_right, 'R'),
+                 (actions.boost, 'B'), (actions.shoot, 'S')];
+    flags.iter().map(|&(active, c)| if active { c } else { '-' }).collect()
+}
+
+/// Parses the textual representation of the actions
+fn decode_actions(text: &str) -> Option<Actions> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() != 4 {
+        return None;
+    }
+
+    Some(Actions {
+        rotate_left: chars[0] == 'L',
+        rotate_right: chars[1] == 'R',
+        boost: chars[2] == 'B',
+        shoot: chars[3] == 'S'
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use drawing::Point;
+    use game::{Actions, Mode};
+    use level::Level;
+    use models::{Obstacle, Shape};
+    use super::Replay;
+
+    /// A replay saved to a file reads back the same, including the level it was played in
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(42, 60, Mode::Coop);
+        replay.level = Some(Level {
+            obstacles: vec![Obstacle::new(Point::new(500.0, 300.0), Shape::Circle(60.0), false)],
+            spawn_points: vec![Point::new(100.0, 100.0)],
+            walls: true,
+            .. Level::default()
+        });
+        let shooting = Actions { shoot: true, .. Actions::default() };
+        for i in 0..10 {
+            replay.record(&[Actions::default(), if i < 5 { shooting.clone() } else { Actions::default() }]);
+        }
+
+        let path = env::temp_dir().join("rocket-replay-test.replay");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+
+        assert_eq!((loaded.seed, loaded.ups), (42, 60));
+        assert_eq!(loaded.mode, Mode::Coop);
+        assert!(loaded.inputs == replay.inputs);
+        let level = loaded.level.unwrap();
+        assert_eq!(level.obstacles[0].position.x, 500.0);
+        assert_eq!(level.spawn_points.len(), 1);
+        assert!(level.walls);
+    }
+}
@@ -0,0 +1,99 @@
+//! A rolling history of the game, used to rewind time
+
+use std::collections::VecDeque;
+
+/// A ring buffer of moments of the game, taken every few updates
+///
+/// Once the buffer is full, the oldest moment is dropped to make room for
+/// the newest one
+#[derive(Clone)]
+pub struct History<T> {
+    moments: VecDeque<T>,
+    /// The maximum amount of moments kept
+    capacity: usize,
+    /// The amount of updates between two moments
+    interval: u32,
+    /// The amount of updates since the last moment was taken
+    since: u32
+}
+
+impl<T> History<T> {
+    /// Returns an empty history that keeps `capacity` moments, taken every `interval` updates
+    pub fn new(capacity: usize, interval: u32) -> History<T> {
+        History { moments: VecDeque::with_capacity(capacity), capacity: capacity, interval: interval, since: 0 }
+    }
+
+    /// Returns the amount of updates between two moments
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Returns true if the history keeps any moments at all
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Counts an update and returns true if it's time to take a moment
+    pub fn tick(&mut self) -> bool {
+        self.since += 1;
+        if self.since >= self.interval {
+            self.since = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds a moment, dropping the oldest one if the history is full
+    pub fn push(&mut self, moment: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.moments.len() == self.capacity {
+            self.moments.pop_front();
+        }
+        self.moments.push_back(moment);
+    }
+
+    /// Returns the newest moment, if any
+    pub fn latest(&self) -> Option<&T> {
+        self.moments.back()
+    }
+
+    /// Removes and returns the newest moment, if any
+    pub fn pop(&mut self) -> Option<T> {
+        self.since = 0;
+        self.moments.pop_back()
+    }
+
+    /// Returns the oldest moment, if any
+    pub fn oldest(&self) -> Option<&T> {
+        self.moments.front()
+    }
+
+    /// Forgets every moment
+    pub fn clear(&mut self) {
+        self.moments.clear();
+        self.since = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use piston::input::Key;
+
+    use drawing::Size;
+    use game::{Game, Mode, ScaleMode};
+
+    /// Holding the rewind key does nothing in a game that keeps no history
+    #[test]
+    fn disabled() {
+        let mut game = Game::new(Size::new(480.0, 300.0), ScaleMode::Letterbox, Mode::Single, 1).unwrap();
+        game.disable_rewind();
+        game.key_press(Key::Backspace);
+        for _ in 0..60 {
+            game.update(1.0 / 60.0);
+        }
+        assert!(game.world().time > 0.9);
+    }
+}
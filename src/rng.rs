@@ -0,0 +1,44 @@
+//! The random number generator used by the game
+
+use rand::Rng;
+
+/// A small and fast random number generator (xorshift128)
+///
+/// Unlike the thread-local generator from `rand`, this one is created from a
+/// seed. Starting two games with the same seed and feeding them the same input
+/// produces exactly the same game, which is what makes replays possible
+#[derive(Clone, Hash, RustcEncodable, RustcDecodable)]
+pub struct GameRng {
+    state: [u32; 4]
+}
+
+impl GameRng {
+    /// Returns a generator initialized from the given seed
+    pub fn new(seed: u64) -> GameRng {
+        // Spread the bits of the seed over the whole state (splitmix64), so
+        // similar seeds give very different sequences. The state must never
+        // be all zeros, which splitmix64 guarantees for two consecutive outputs
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^ (z >> 31)
+        };
+
+        let (a, b) = (next(), next());
+        GameRng { state: [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32] }
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.state[0] ^ (self.state[0] << 11);
+        self.state[0] = self.state[1];
+        self.state[1] = self.state[2];
+        self.state[2] = self.state[3];
+        self.state[3] = self.state[3] ^ (self.state[3] >> 19) ^ t ^ (t >> 8);
+        self.state[3]
+    }
+}
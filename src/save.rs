@@ -0,0 +1,140 @@
+//! Saving and loading games
+//!
+//! Save files contain the whole state of the game as JSON, including the state
+//! of the random number generator, so a loaded game continues exactly like the
+//! original one would have. The state is wrapped in an object with the version
+//! of the format:
+//!
+//! ```text
+//! { "version": 7, "state": { "world": ..., "mode": ..., ... } }
+//! ```
+//!
+//! Since the format follows the models closely, the version must be increased
+//! whenever a model that is saved changes.
+//!
+//! Floating point numbers are written as strings, like `"0.1"`. The JSON
+//! parser rounds the numbers it reads, while strings are parsed exactly, so the
+//! loaded game doesn't drift apart from the saved one.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{self, Json};
+
+use game::{Game, State};
+
+/// The version of the file format
+const VERSION: u64 = 7;
+
+/// Saves the game to a file
+pub fn save<P: AsRef<Path>>(game: &Game, path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    let state = try!(json::encode(&game.state()).map_err(|e| format!("Unable to save the game: {}", e)));
+    let contents = format!("{{\"version\":{},\"state\":{}}}", VERSION, quote_floats(&state));
+    File::create(path).and_then(|mut f| f.write_all(contents.as_bytes()))
+                      .map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+}
+
+/// Replaces the state of the game with the one saved in a file
+pub fn load<P: AsRef<Path>>(game: &mut Game, path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    let mut source = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+             .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+    let state = try!(decode(&source).map_err(|e| format!("Invalid save file {}: {}", path.display(), e)));
+    game.restore(state);
+    Ok(())
+}
+
+/// Returns the state contained in the contents of a save file
+fn decode(source: &str) -> Result<State, String> {
+    let json = try!(Json::from_str(source).map_err(|e| e.to_string()));
+
+    match json.find("version").and_then(|v| v.as_u64()) {
+        Some(VERSION) => {}
+        Some(version) => return Err(format!("saved with version {} of the format, expected {}", version, VERSION)),
+        None => return Err("missing version".to_string())
+    }
+
+    let state = try!(json.find("state").cloned().ok_or("missing state".to_string()));
+    State::decode(&mut json::Decoder::new(state)).map_err(|e| e.to_string())
+}
+
+/// Puts the floating point numbers of the JSON text in quotes
+///
+/// Integers are left alone, and so is everything inside strings
+fn quote_floats(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            // Copy the string up to its closing quote, skipping escaped quotes
+            output.push(c);
+            while let Some(c) = chars.next() {
+                output.push(c);
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        output.push(escaped);
+                    }
+                } else if c == '"' {
+                    break;
+                }
+            }
+        } else if c == '-' || c.is_digit(10) {
+            let mut number = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if c.is_digit(10) || "-+.eE".contains(c) {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            // The encoder writes every float with a decimal point
+            if number.contains('.') {
+                output.push_str(&format!("\"{}\"", number));
+            } else {
+                output.push_str(&number);
+            }
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use drawing::Size;
+    use game::{Actions, Game, Mode, ScaleMode};
+
+    /// Saves a game halfway, loads it into another one and checks that both
+    /// continue exactly the same
+    #[test]
+    fn resume() {
+        let size = Size::new(480.0, 300.0);
+        let mut original = Game::new(size.clone(), ScaleMode::Letterbox, Mode::Coop, 11).unwrap();
+        original.set_actions(0, Actions { rotate_left: true, shoot: true, .. Actions::default() });
+        original.set_actions(1, Actions { boost: true, shoot: true, .. Actions::default() });
+        for _ in 0..240 {
+            original.update(1.0 / 60.0);
+        }
+
+        let path = env::temp_dir().join("rocket-save-test.json");
+        super::save(&original, &path).unwrap();
+        let mut loaded = Game::new(size, ScaleMode::Letterbox, Mode::Single, 0).unwrap();
+        super::load(&mut loaded, &path).unwrap();
+        assert_eq!(loaded.checksum(), original.checksum());
+
+        for _ in 0..240 {
+            original.update(1.0 / 60.0);
+            loaded.update(1.0 / 60.0);
+        }
+        assert_eq!(loaded.checksum(), original.checksum());
+    }
+}
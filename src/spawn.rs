@@ -0,0 +1,120 @@
+//! Finding safe places for new enemies to appear
+//!
+//! An enemy that appears right next to a player kills them before they can
+//! react, so new enemies keep some distance from the living players and don't
+//! land on top of other enemies or inside obstacles. Among the safe places, the ones the players
+//! can't see and the ones along the edges of the world are preferred, so
+//! enemies seem to fly in from somewhere instead of popping up.
+
+use std::f64;
+
+use rand::Rng;
+
+use drawing::{Point, Size};
+use models::{Player, World};
+use traits::{Collide, Position};
+
+/// Enemies never appear closer than this to a living player, in pixels
+pub const MIN_DISTANCE: f64 = 250.0;
+
+/// The amount of random positions considered when looking for a place
+const CANDIDATES: usize = 24;
+
+/// Positions closer than this to an edge of the world count as edge positions, in pixels
+const EDGE_BAND: f64 = 100.0;
+
+/// Returns a place for an enemy of the given radius to appear, or `None` if
+/// there is no safe place left in the world
+///
+/// The players are assumed to see a region of size `view` around the center
+/// of the camera. It must not depend on the window, or neither would the
+/// simulation
+pub fn find<R: Rng>(rng: &mut R, radius: f64, world: &World, view: &Size) -> Option<Point> {
+    let mut best: Option<(Point, (bool, bool, f64))> = None;
+    for i in 0..CANDIDATES {
+        // Half of the candidates are drawn along the edges, the rest anywhere
+        let candidate = if i % 2 == 0 { edge_point(rng, &world.size) } else { Point::random(rng, world.size.clone()) };
+
+        let distance = clearance(&candidate, &world.players, &world.size);
+        let overlaps = world.enemies.iter().chain(world.telegraphs.iter().map(|t| &t.enemy)).any(|e| {
+            let reach = radius + e.radius();
+            e.position().squared_distance_to(&candidate) < reach * reach
+        }) || world.obstacles.iter().any(|o| o.push_out(&candidate, radius).is_some());
+        if distance < MIN_DISTANCE || overlaps {
+            continue;
+        }
+
+        let score = (!is_visible(&candidate, &world.camera.center, view, &world.size),
+                     is_near_edge(&candidate, &world.size), distance);
+        if best.as_ref().map_or(true, |&(_, ref top)| score > *top) {
+            best = Some((candidate, score));
+        }
+    }
+    best.map(|(point, _)| point)
+}
+
+/// Returns the distance from the point to the nearest living player, taking
+/// into account that the world wraps around
+pub fn clearance(point: &Point, players: &[Player], size: &Size) -> f64 {
+    players.iter().filter(|p| p.is_alive()).map(|p| {
+        let offset = size.offset(point, &p.position());
+        (offset.x * offset.x + offset.y * offset.y).sqrt()
+    }).fold(f64::INFINITY, |a, b| a.min(b))
+}
+
+/// Returns a random point close to one of the edges of the world
+fn edge_point<R: Rng>(rng: &mut R, size: &Size) -> Point {
+    let band_x = EDGE_BAND.min(size.width);
+    let band_y = EDGE_BAND.min(size.height);
+    match rng.gen_range(0, 4) {
+        0 => Point::new(rng.gen_range(0.0, band_x), size.random_y(rng)),
+        1 => Point::new(size.width - rng.gen_range(0.0, band_x), size.random_y(rng)),
+        2 => Point::new(size.random_x(rng), rng.gen_range(0.0, band_y)),
+        _ => Point::new(size.random_x(rng), size.height - rng.gen_range(0.0, band_y))
+    }
+}
+
+/// Returns true if the point is within the region of size `view` around the
+/// center, taking into account that the world wraps around
+fn is_visible(point: &Point, center: &Point, view: &Size, size: &Size) -> bool {
+    let offset = size.offset(center, point);
+    offset.x.abs() <= view.width / 2.0 && offset.y.abs() <= view.height / 2.0
+}
+
+/// Returns true if the point is close to one of the edges of the world
+fn is_near_edge(point: &Point, size: &Size) -> bool {
+    point.x < EDGE_BAND || point.y < EDGE_BAND
+    || point.x > size.width - EDGE_BAND || point.y > size.height - EDGE_BAND
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::{Point, Size};
+    use models::{Enemy, Vector, World};
+    use models::enemy::Kind;
+    use rng::GameRng;
+    use traits::Position;
+    use super::{clearance, find, MIN_DISTANCE};
+
+    /// Places many enemies in a world and checks that none of them is too
+    /// close to the player or overlaps another one, and that nothing is placed
+    /// once the world is too small to keep the distance
+    #[test]
+    fn keeps_distance() {
+        let mut rng = GameRng::new(5);
+        let view = Size::new(800.0, 500.0);
+        let mut world = World::new(&mut rng, Size::new(1600.0, 1000.0), view.clone(), 1);
+        world.players[0].vector = Vector::new(Point::new(800.0, 500.0), 0.0);
+
+        for _ in 0..40 {
+            let point = find(&mut rng, 10.0, &world, &view).unwrap();
+            assert!(clearance(&point, &world.players, &world.size) >= MIN_DISTANCE);
+            assert!(world.enemies.iter().all(|e| e.position().squared_distance_to(&point) >= 20.0 * 20.0));
+            world.enemies.push(Enemy::new(Vector::new(point, 0.0), Kind::Chaser));
+        }
+
+        world.size = Size::new(300.0, 300.0);
+        world.players[0].vector = Vector::new(Point::new(150.0, 150.0), 0.0);
+        assert!(find(&mut rng, 10.0, &world, &view).is_none());
+    }
+}
@@ -53,7 +53,12 @@ pub trait Advance: Position {
     /// around the given bounds
     fn advance_wrapping(&mut self, units: f64, bounds: Size) {
         self.advance(units);
+        self.wrap(bounds);
+    }
 
+    /// Moves the object back inside the given bounds if it left them,
+    /// entering through the opposite edge
+    fn wrap(&mut self, bounds: Size) {
         fn wrap(k: &mut f64, bound: f64) {
             if *k < 0.0 {
                 *k += bound;
@@ -0,0 +1,253 @@
+//! Waves of enemies that arrive in formation, described in a data file
+//!
+//! The waves are loaded at startup from a TOML file (see `resources/waves.toml`
+//! for the format), so new formations can be tried without recompiling. Each
+//! wave is made of groups of enemies of the same kind, placed around a player
+//! or along an edge of the world following a pattern.
+
+use std::cmp;
+use std::f64;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use rand::Rng;
+use rustc_serialize::Decodable;
+use toml;
+
+use drawing::{Point, Size};
+use models::{Enemy, Player, Vector};
+use models::enemy::Kind;
+use spawn;
+use traits::Position;
+
+/// How far from the edges of the world enemies are placed, in pixels
+const MARGIN: f64 = 20.0;
+
+/// The waves of the game
+#[derive(Clone)]
+pub struct Waves {
+    /// The seconds a wave is announced before its enemies appear
+    pub warning: f64,
+    waves: Vec<Wave>
+}
+
+#[derive(Clone)]
+struct Wave {
+    /// The seconds between the previous wave and this one
+    delay: f64,
+    groups: Vec<Group>
+}
+
+/// A group of enemies of the same kind, placed following a pattern
+#[derive(Clone)]
+struct Group {
+    pattern: Pattern,
+    kind: Kind,
+    count: usize,
+    /// The distance from the player, for rings and Vs
+    distance: f64,
+    /// The distance between enemies, for lines and Vs
+    spacing: f64,
+    /// The seconds the enemies fly straight ahead before chasing the players
+    charge: f64
+}
+
+#[derive(Clone, Copy)]
+enum Pattern {
+    Ring,
+    Line,
+    V
+}
+
+/// The contents of the data file
+#[derive(RustcDecodable)]
+struct Source {
+    warning: f64,
+    wave: Vec<WaveSource>
+}
+
+#[derive(RustcDecodable)]
+struct WaveSource {
+    delay: f64,
+    group: Vec<GroupSource>
+}
+
+#[derive(RustcDecodable)]
+struct GroupSource {
+    pattern: String,
+    kind: String,
+    count: usize,
+    distance: Option<f64>,
+    spacing: Option<f64>,
+    charge: Option<f64>
+}
+
+impl Waves {
+    /// Loads the waves defined in the given file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Waves, String> {
+        let path = path.as_ref();
+        let mut source = String::new();
+        try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+                 .map_err(|e| format!("Unable to read {}: {}", path.display(), e)));
+        parse(&source).map_err(|e| format!("Invalid waves file {}: {}", path.display(), e))
+    }
+
+    /// Returns the seconds between the given wave and the previous one
+    pub fn delay(&self, wave: usize) -> f64 {
+        self.waves.get(wave % cmp::max(self.waves.len(), 1)).map_or(f64::INFINITY, |w| w.delay)
+    }
+
+    /// Returns the enemies of the given wave, placed around a random living
+    /// player. Waves start again from the first one after the last one
+    ///
+    /// Enemies of a formation that would appear too close to a player are left
+    /// out, except for bosses: the warning gives the players time to get away
+    pub fn spawn<R: Rng>(&self, wave: usize, rng: &mut R, players: &[Player], size: &Size) -> Vec<Enemy> {
+        let living: Vec<_> = players.iter().filter(|p| p.is_alive()).collect();
+        if self.waves.is_empty() || living.is_empty() {
+            return vec![];
+        }
+
+        let mut enemies = vec![];
+        for group in &self.waves[wave % self.waves.len()].groups {
+            let target = living[rng.gen_range(0, living.len())].position();
+            let vectors = match group.pattern {
+                Pattern::Ring => ring(group, &target, rng),
+                Pattern::Line => line(group, &target, size, rng),
+                Pattern::V => v(group, &target, rng)
+            };
+            for vector in vectors {
+                let position = wrap(vector.position, size);
+                if group.kind != Kind::Boss && spawn::clearance(&position, players, size) < spawn::MIN_DISTANCE {
+                    continue;
+                }
+                let mut enemy = Enemy::new(Vector::new(position, vector.direction), group.kind);
+                enemy.charge = group.charge;
+                enemies.push(enemy);
+            }
+        }
+        enemies
+    }
+}
+
+/// Returns the waves described by the contents of a data file
+fn parse(source: &str) -> Result<Waves, String> {
+    let mut parser = toml::Parser::new(source);
+    let table = match parser.parse() {
+        Some(table) => table,
+        None => {
+            let errors: Vec<_> = parser.errors.iter().map(|e| e.desc.clone()).collect();
+            return Err(errors.join(", "));
+        }
+    };
+    let source = try!(Source::decode(&mut toml::Decoder::new(toml::Value::Table(table))).map_err(|e| e.to_string()));
+
+    let mut waves = vec![];
+    for wave in source.wave {
+        let mut groups = vec![];
+        for group in wave.group {
+            groups.push(Group {
+                pattern: match &group.pattern[..] {
+                    "ring" => Pattern::Ring,
+                    "line" => Pattern::Line,
+                    "v" => Pattern::V,
+                    pattern => return Err(format!("unknown pattern `{}`", pattern))
+                },
+                kind: match &group.kind[..] {
+                    "chaser" => Kind::Chaser,
+                    "swarmer" => Kind::Swarmer,
+                    "hunter" => Kind::Hunter,
+                    "boss" => Kind::Boss,
+                    kind => return Err(format!("unknown kind `{}`", kind))
+                },
+                count: group.count,
+                distance: group.distance.unwrap_or(0.0),
+                spacing: group.spacing.unwrap_or(0.0),
+                charge: group.charge.unwrap_or(0.0)
+            });
+        }
+        waves.push(Wave { delay: wave.delay, groups: groups });
+    }
+    Ok(Waves { warning: source.warning, waves: waves })
+}
+
+/// Places the enemies evenly around a circle centered on the target, facing it
+fn ring<R: Rng>(group: &Group, target: &Point, rng: &mut R) -> Vec<Vector> {
+    let offset = rng.gen_range(0.0, 2.0 * PI);
+    (0..group.count).map(|i| {
+        let angle = offset + 2.0 * PI * i as f64 / group.count as f64;
+        Vector::new(Point::new(target.x + angle.cos() * group.distance, target.y + angle.sin() * group.distance),
+                    angle + PI)
+    }).collect()
+}
+
+/// Places the enemies along a random edge of the world, centered in front of
+/// the target and facing the opposite edge
+fn line<R: Rng>(group: &Group, target: &Point, size: &Size, rng: &mut R) -> Vec<Vector> {
+    let edge = rng.gen_range(0, 4);
+    let width = group.spacing * (group.count as f64 - 1.0);
+    (0..group.count).map(|i| {
+        let along = group.spacing * i as f64 - width / 2.0;
+        match edge {
+            0 => Vector::new(Point::new(MARGIN, target.y + along), 0.0),
+            1 => Vector::new(Point::new(size.width - MARGIN, target.y + along), PI),
+            2 => Vector::new(Point::new(target.x + along, MARGIN), PI / 2.0),
+            _ => Vector::new(Point::new(target.x + along, size.height - MARGIN), -PI / 2.0)
+        }
+    }).collect()
+}
+
+/// Places the enemies in a V pointing at the target from a random direction,
+/// with the leader at the tip and the rest alternating between the wings
+fn v<R: Rng>(group: &Group, target: &Point, rng: &mut R) -> Vec<Vector> {
+    let angle = rng.gen_range(0.0, 2.0 * PI);
+    let tip = Point::new(target.x + angle.cos() * group.distance, target.y + angle.sin() * group.distance);
+    let heading = angle + PI;
+    (0..group.count).map(|i| {
+        let row = ((i + 1) / 2) as f64 * group.spacing;
+        let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+        let back = Point::new(-heading.cos() * row, -heading.sin() * row);
+        let wing = Point::new(-heading.sin() * row * side, heading.cos() * row * side);
+        Vector::new(Point::new(tip.x + back.x + wing.x, tip.y + back.y + wing.y), heading)
+    }).collect()
+}
+
+/// Wraps the point around the edges of the world, like moving objects do
+fn wrap(point: Point, size: &Size) -> Point {
+    fn wrap(k: f64, bound: f64) -> f64 {
+        (k % bound + bound) % bound
+    }
+
+    Point::new(wrap(point.x, size.width), wrap(point.y, size.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use drawing::{color, Size};
+    use models::Player;
+    use models::enemy::Kind;
+    use rng::GameRng;
+    use traits::Position;
+    use super::Waves;
+
+    /// Loads the waves of the game and checks that every enemy of the first
+    /// waves is placed inside the world
+    #[test]
+    fn spawn_inside_world() {
+        let waves = Waves::load("resources/waves.toml").unwrap();
+        let size = Size::new(2048.0, 1200.0);
+        let mut rng = GameRng::new(3);
+        let players = [Player::random(&mut rng, size.clone(), color::RED)];
+
+        for wave in 0..8 {
+            let enemies = waves.spawn(wave, &mut rng, &players, &size);
+            assert!(!enemies.is_empty());
+            for enemy in &enemies {
+                assert!(size.contains(enemy.position()));
+            }
+        }
+        assert!(waves.spawn(0, &mut rng, &players, &size).iter().all(|e| e.kind == Kind::Chaser));
+    }
+}